@@ -0,0 +1,91 @@
+use crate::{GodMode, Hidden, Map, Position, Renderable, TileType};
+use rltk::{FontCharType, Point, Rltk, RGB};
+use specs::prelude::*;
+
+/// Size of the map viewport on the console. The UI panel takes the rows below it.
+pub const VIEW_WIDTH: i32 = 80;
+pub const VIEW_HEIGHT: i32 = 43;
+
+/// World coordinates of the viewport's top left corner. The camera keeps
+/// the player in the middle of the screen.
+pub fn camera_origin(ecs: &World) -> Point {
+    let player_pos = ecs.fetch::<Point>();
+    Point::new(
+        player_pos.x - VIEW_WIDTH / 2,
+        player_pos.y - VIEW_HEIGHT / 2,
+    )
+}
+
+pub fn screen_to_world(ecs: &World, screen: Point) -> Point {
+    let origin = camera_origin(ecs);
+    Point::new(origin.x + screen.x, origin.y + screen.y)
+}
+
+/// Where `world` is drawn on the console, if it is inside the viewport.
+pub fn world_to_screen(ecs: &World, world: Point) -> Option<Point> {
+    let origin = camera_origin(ecs);
+    let screen = Point::new(world.x - origin.x, world.y - origin.y);
+
+    if screen.x < 0 || screen.x >= VIEW_WIDTH || screen.y < 0 || screen.y >= VIEW_HEIGHT {
+        return None;
+    }
+    Some(screen)
+}
+
+/// Draws the part of the map around the player, then the entities on it.
+pub fn render_camera(ecs: &World, ctx: &mut Rltk) {
+    let map = ecs.fetch::<Map>();
+    let godmode = ecs.fetch::<GodMode>();
+    let origin = camera_origin(ecs);
+
+    for screen_y in 0..VIEW_HEIGHT {
+        for screen_x in 0..VIEW_WIDTH {
+            let x = origin.x + screen_x;
+            let y = origin.y + screen_y;
+
+            if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                // Beyond the edge of the map.
+                ctx.set(
+                    screen_x,
+                    screen_y,
+                    RGB::named(rltk::GRAY),
+                    RGB::named(rltk::BLACK),
+                    rltk::to_cp437('·'),
+                );
+                continue;
+            }
+
+            let index = map.map_index(x, y);
+            if map.revealed_tiles[index] || godmode.0 {
+                let (glyph, mut fg) = tile_glyph(map.tiles[index]);
+                if !map.visible_tiles[index] {
+                    fg = fg.to_greyscale()
+                }
+                ctx.set(screen_x, screen_y, fg, RGB::named(rltk::BLACK), glyph);
+            }
+        }
+    }
+
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let hidden = ecs.read_storage::<Hidden>();
+
+    for (pos, render, _visible) in (&positions, &renderables, !&hidden).join() {
+        let index = map.map_index(pos.x, pos.y);
+        if !map.visible_tiles[index] {
+            continue;
+        }
+
+        if let Some(screen) = world_to_screen(ecs, Point::new(pos.x, pos.y)) {
+            ctx.set(screen.x, screen.y, render.fg, render.bg, render.glyph);
+        }
+    }
+}
+
+fn tile_glyph(tile: TileType) -> (FontCharType, RGB) {
+    match tile {
+        TileType::Floor => (rltk::to_cp437('.'), RGB::from_f32(0.5, 0.5, 0.5)),
+        TileType::Wall => (rltk::to_cp437('#'), RGB::from_f32(0., 1.0, 0.)),
+        TileType::DownStairs => (rltk::to_cp437('>'), RGB::from_f32(0., 1.0, 1.0)),
+    }
+}
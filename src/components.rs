@@ -1,43 +1,199 @@
-use rltk::{Point, RGB, NavigationPath};
+use rltk::{Point, RGB, NavigationPath, RandomNumberGenerator};
+use serde::{Deserialize, Serialize};
+// The ConvertSaveload derive names its error type `NoError`.
+use std::convert::Infallible as NoError;
 use specs::prelude::*;
-use specs_derive::Component;
+use specs::saveload::{ConvertSaveload, Marker};
+use specs_derive::{Component, ConvertSaveload};
 
-#[derive(Component, Debug)]
+use crate::{GameLog, Map, RunState};
+
+/// Marks the entities that are written to the save file.
+pub struct SerializeMe;
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct LastPathUpdate {
     pub nb_frames: usize
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct InInventory {
     pub owner: Entity
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct WantsToPickupItem {
     pub collected_by: Entity,
     pub item: Entity
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToUseItem {
+    pub item: Entity,
+    pub target: Option<Point>,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToDropItem {
+    pub item: Entity
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToDestroyItem {
+    pub item: Entity
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Item {}
 
-#[derive(Component, Debug)]
+/// Items that are used up when they are used.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Consumable {}
+
+/// Eating it makes you well fed again.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ProvidesFood {}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum HungerState {
+    WellFed,
+    Normal,
+    Hungry,
+    Starving,
+}
+
+/// Counts down the turns until the next, hungrier, state.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct HungerClock {
+    pub state: HungerState,
+    pub duration: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Ranged {
+    pub range: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct InflictsDamage {
+    pub damage: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct AreaOfEffect {
+    pub radius: i32,
+}
+
+// Status effects. Put on an item, they are applied to whatever the item is used on.
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct Confusion {
+    pub turns: i32,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct Poisoned {
+    pub dmg_per_turn: i32,
+    pub turns: i32,
+    /// Whoever did the poisoning, credited with the kill.
+    pub source: Poisoner,
+}
+
+/// Whoever did some poisoning, if anyone did. Saved through the markers
+/// like any other entity, and forgotten if they are gone by then.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Poisoner(pub Option<Entity>);
+
+impl<M> ConvertSaveload<M> for Poisoner
+where
+    M: Serialize + for<'de> Deserialize<'de>,
+{
+    type Data = Option<M>;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        Ok(self.0.and_then(ids))
+    }
+
+    fn convert_from<F>(data: Self::Data, ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        Ok(Poisoner(data.and_then(ids)))
+    }
+}
+
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct Asleep {
+    pub turns: i32,
+}
+
+/// How quickly an entity gets turns: `speed` energy is gained every tick
+/// and a turn costs `TURN_ENERGY`.
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct Initiative {
+    pub speed: i32,
+    pub energy: i32,
+}
+
+/// Marks the entities that get to act this tick. Not saved: turns are
+/// handed out again once a loaded game starts ticking.
+#[derive(Component, Debug, Clone)]
+pub struct MyTurn {}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum EquipmentSlot {
+    Melee,
+    Shield,
+}
+
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct Equippable {
+    pub slot: EquipmentSlot,
+}
+
+#[derive(Component, Debug, Clone, ConvertSaveload)]
+pub struct Equipped {
+    pub owner: Entity,
+    pub slot: EquipmentSlot,
+}
+
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct MeleePowerBonus {
+    pub power: i32,
+}
+
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+pub struct DefenseBonus {
+    pub defense: i32,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Potion {
     pub heal_amount: i32,
 }
 
+/// Damage waiting to be applied, with whoever dealt each hit. It only lives
+/// until the next DamageSystem run, so it isn't saved.
 #[derive(Component, Debug, Clone)]
 pub struct SufferDamage {
-    pub amount: Vec<i32>,
+    pub amount: Vec<(i32, Option<Entity>)>,
 }
 
 impl SufferDamage {
-    pub fn new_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32) {
+    pub fn new_damage(
+        store: &mut WriteStorage<SufferDamage>,
+        victim: Entity,
+        amount: i32,
+        source: Option<Entity>,
+    ) {
         if let Some(suffering) = store.get_mut(victim) {
-            suffering.amount.push(amount);
+            suffering.amount.push((amount, source));
         } else {
             let dmg = SufferDamage {
-                amount: vec![amount],
+                amount: vec![(amount, source)],
             };
             store
                 .insert(victim, dmg)
@@ -46,12 +202,25 @@ impl SufferDamage {
     }
 }
 
+/// Put on a creature by the hit that took it below 1 hp, so `delete_the_dead`
+/// knows who to reward.
 #[derive(Component, Debug, Clone)]
+pub struct KillingBlow {
+    pub attacker: Entity,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Experience {
+    pub level: i32,
+    pub xp: i32,
+}
+
+#[derive(Component, Debug, Clone, ConvertSaveload)]
 pub struct WantsToMelee {
     pub target: Entity,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct CombatStats {
     pub max_hp: i32,
     pub hp: i32,
@@ -59,42 +228,102 @@ pub struct CombatStats {
     pub power: i32,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct BlocksTile {}
 
-#[derive(Component)]
+/// Stops sight like a wall does, while it's there.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct BlocksVisibility {}
+
+/// Closed doors block movement and sight. Bumping into one opens it,
+/// unless it is locked and the one bumping has no key.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Door {
+    pub open: bool,
+    pub locked: bool,
+}
+
+/// Unlocks a door, and is used up doing so.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Key {}
+
+#[derive(Component, Debug, Clone, ConvertSaveload)]
+pub struct WantsToOpenDoor {
+    pub door: Entity,
+}
+
+/// Not drawn until the player spots it. Monsters only learn of a trap once
+/// it is out in the open.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Hidden {}
+
+/// Goes off when something steps onto its tile.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct EntryTrigger {}
+
+/// Triggers that go off only once, and are gone afterwards.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct SingleActivation {}
+
+/// Sends whoever triggers it to a random spot on the level.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Teleports {}
+
+/// Put on whatever moved this turn, for the entry triggers to check.
+#[derive(Component, Debug, Clone)]
+pub struct EntityMoved {}
+
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Name {
     pub name: String,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Monster {
     pub last_known_player_pos: Option<Point>,
+    #[serde(skip)]
     pub last_pathfind: Option<NavigationPath>,
-    pub next_step: usize
+    pub next_step: usize,
+    /// Map indices of the traps this monster has seen, which it walks around.
+    #[serde(default)]
+    pub known_traps: Vec<usize>,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Viewshed {
     pub visible_tiles: Vec<rltk::Point>,
     pub range: i32,
     pub dirty: bool,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy, Serialize, Deserialize)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Renderable {
     pub glyph: rltk::FontCharType,
     pub fg: RGB,
     pub bg: RGB,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Player {
     pub number_of_moves: i32,
 }
+
+/// Carries the resources that go in the save file along with the entities.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct SerializationHelper {
+    pub map: Map,
+    pub log: GameLog,
+    pub god_mode: bool,
+    pub rng: RandomNumberGenerator,
+    pub seed: u64,
+    pub kills: i32,
+    pub last_hurt_by: String,
+    pub turns: i32,
+    pub run_state: RunState,
+}
@@ -1,22 +1,60 @@
-use crate::{GameLog, Name, Player};
+use crate::{
+    GameLog, LevelUpBanner, LogCategory, LogEntry, Monster, Name, Player, Renderable, RunState,
+    RunStats,
+};
 
-use super::{CombatStats, SufferDamage};
+use super::{Asleep, CombatStats, Experience, KillingBlow, SufferDamage};
 
+use rltk::RGB;
 use specs::prelude::*;
 
+/// XP a kill is worth, per level of the victim.
+const XP_PER_VICTIM_LEVEL: i32 = 100;
+const LEVEL_UP_HP: i32 = 10;
+const LEVEL_UP_POWER: i32 = 1;
+/// How many of the player's turns the level up banner stays up for.
+const LEVEL_UP_BANNER_TURNS: i32 = 5;
+
+/// XP needed to go from `level` to the next one.
+pub fn xp_to_next_level(level: i32) -> i32 {
+    level * 1000
+}
+
 pub struct DamageSystem {}
 
 impl<'a> System<'a> for DamageSystem {
     type SystemData = (
         WriteStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
+        WriteStorage<'a, Asleep>,
+        WriteStorage<'a, KillingBlow>,
+        Entities<'a>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut stats, mut damage) = data;
+        let (mut stats, mut damage, mut asleep, mut killing_blows, entities) = data;
 
-        for (stats, damage) in (&mut stats, &damage).join() {
-            stats.hp -= damage.amount.iter().sum::<i32>();
+        for (entity, stats, damage) in (&entities, &mut stats, &damage).join() {
+            for (amount, source) in damage.amount.iter() {
+                let was_alive = stats.hp > 0;
+                stats.hp -= amount;
+
+                if was_alive && stats.hp < 1 {
+                    if let Some(attacker) = source {
+                        killing_blows
+                            .insert(
+                                entity,
+                                KillingBlow {
+                                    attacker: *attacker,
+                                },
+                            )
+                            .expect("Unable to insert killing blow");
+                    }
+                }
+            }
+
+            // Getting hurt wakes you up.
+            asleep.remove(entity);
         }
 
         damage.clear();
@@ -25,13 +63,21 @@ impl<'a> System<'a> for DamageSystem {
 
 pub fn delete_the_dead(ecs: &mut World) {
     let mut dead: Vec<Entity> = Vec::new();
+    let mut xp_awards: Vec<(Entity, i32)> = Vec::new();
 
     {
         let combat_stats = ecs.read_storage::<CombatStats>();
         let entities = ecs.entities();
         let players = ecs.read_storage::<Player>();
         let names = ecs.read_storage::<Name>();
+        let monsters = ecs.read_storage::<Monster>();
+        let renderables = ecs.read_storage::<Renderable>();
+        let killing_blows = ecs.read_storage::<KillingBlow>();
+        let experience = ecs.read_storage::<Experience>();
         let mut log = ecs.write_resource::<GameLog>();
+        let mut runstate = ecs.write_resource::<RunState>();
+        let mut run_stats = ecs.write_resource::<RunStats>();
+        let player_entity = ecs.fetch::<Entity>();
 
         for (entity, stats) in (&entities, &combat_stats).join() {
             if stats.hp < 1 {
@@ -41,13 +87,35 @@ pub fn delete_the_dead(ecs: &mut World) {
                     None => {
                         let victim_name = names.get(entity);
                         if let Some(victim_name) = victim_name {
-                            let death_message = format!("{} is dead.", victim_name.name);
-                            log.entries.push(death_message);
+                            log.log(
+                                LogEntry::new(LogCategory::Combat)
+                                    .name(&victim_name.name, renderables.get(entity))
+                                    .text(" is dead."),
+                            );
+                        }
+                        // Only the player's own kills count, items they used included.
+                        let killed_by_player = killing_blows
+                            .get(entity)
+                            .is_some_and(|blow| blow.attacker == *player_entity);
+                        if monsters.get(entity).is_some() && killed_by_player {
+                            run_stats.kills += 1;
+                        }
+                        if let (Some(blow), Some(victim_xp)) =
+                            (killing_blows.get(entity), experience.get(entity))
+                        {
+                            xp_awards.push((blow.attacker, victim_xp.level * XP_PER_VICTIM_LEVEL));
                         }
                         dead.push(entity);
                     }
                     Some(_) => {
-                        log.entries.push("You are dead.".to_string());
+                        // The player stays in the world, the game over screen takes over.
+                        if *runstate != RunState::GameOver {
+                            log.log(
+                                LogEntry::new(LogCategory::Combat)
+                                    .colored("You are dead.", RGB::named(rltk::RED)),
+                            );
+                            *runstate = RunState::GameOver;
+                        }
                     }
                 }
             }
@@ -58,4 +126,42 @@ pub fn delete_the_dead(ecs: &mut World) {
         ecs.delete_entity(victim)
             .expect("Unable to delete entity after death");
     }
+
+    for (killer, xp) in xp_awards {
+        award_experience(ecs, killer, xp);
+    }
+}
+
+/// Every level gained raises max hp and power, and heals fully.
+fn award_experience(ecs: &mut World, entity: Entity, xp: i32) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut experience = ecs.write_storage::<Experience>();
+    let mut combat_stats = ecs.write_storage::<CombatStats>();
+
+    let (experience, stats) = match (experience.get_mut(entity), combat_stats.get_mut(entity)) {
+        (Some(experience), Some(stats)) => (experience, stats),
+        _ => return,
+    };
+
+    experience.xp += xp;
+    while experience.xp >= xp_to_next_level(experience.level) {
+        experience.xp -= xp_to_next_level(experience.level);
+        experience.level += 1;
+        stats.max_hp += LEVEL_UP_HP;
+        stats.power += LEVEL_UP_POWER;
+        stats.hp = stats.max_hp;
+
+        if entity == player_entity {
+            let mut log = ecs.write_resource::<GameLog>();
+            log.log(LogEntry::new(LogCategory::System).colored(
+                format!("Congratulations, you are now level {}!", experience.level),
+                RGB::named(rltk::GOLD),
+            ));
+
+            let turns = ecs.fetch::<RunStats>().turns;
+            let mut banner = ecs.write_resource::<LevelUpBanner>();
+            banner.level = experience.level;
+            banner.until_turn = turns + LEVEL_UP_BANNER_TURNS;
+        }
+    }
 }
@@ -0,0 +1,91 @@
+use crate::{
+    BlocksTile, BlocksVisibility, Door, GameLog, InInventory, Key, LogCategory, LogEntry, Name,
+    Renderable, Viewshed, WantsToOpenDoor,
+};
+
+use specs::prelude::*;
+
+/// Opens the doors that were bumped into. A locked door takes a key from
+/// the backpack of whoever opens it.
+pub struct DoorSystem {}
+
+impl<'a> System<'a> for DoorSystem {
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToOpenDoor>,
+        WriteStorage<'a, Door>,
+        WriteStorage<'a, BlocksTile>,
+        WriteStorage<'a, BlocksVisibility>,
+        WriteStorage<'a, Renderable>,
+        WriteStorage<'a, Viewshed>,
+        ReadStorage<'a, Key>,
+        ReadStorage<'a, InInventory>,
+        ReadStorage<'a, Name>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            entities,
+            mut wants_open,
+            mut doors,
+            mut blocks_tile,
+            mut blocks_visibility,
+            mut renderables,
+            mut viewsheds,
+            keys,
+            backpack,
+            names,
+        ) = data;
+
+        for (opener, wants) in (&entities, &wants_open).join() {
+            let door = match doors.get_mut(wants.door) {
+                Some(door) if !door.open => door,
+                _ => continue,
+            };
+
+            if door.locked {
+                let key = (&entities, &keys, &backpack)
+                    .join()
+                    .find(|(_, _, item)| item.owner == opener)
+                    .map(|(key, _, _)| key);
+
+                match key {
+                    Some(key) => {
+                        if opener == *player_entity {
+                            gamelog.log(LogEntry::new(LogCategory::Item).text(format!(
+                                "You unlock the door with the {}.",
+                                names.get(key).unwrap().name
+                            )));
+                        }
+                        entities.delete(key).expect("Delete failed");
+                        door.locked = false;
+                    }
+                    None => {
+                        if opener == *player_entity {
+                            gamelog.system("The door is locked.");
+                        }
+                        continue;
+                    }
+                }
+            }
+
+            door.open = true;
+            blocks_tile.remove(wants.door);
+            blocks_visibility.remove(wants.door);
+            if let Some(render) = renderables.get_mut(wants.door) {
+                render.glyph = rltk::to_cp437('/');
+            }
+
+            // Whoever could see the door may now see through it.
+            for viewshed in (&mut viewsheds).join() {
+                viewshed.dirty = true;
+            }
+        }
+
+        wants_open.clear();
+    }
+}
@@ -1,3 +1,126 @@
+use std::collections::VecDeque;
+
+use rltk::RGB;
+use serde::{Deserialize, Serialize};
+
+use crate::Renderable;
+
+/// The oldest entries are dropped past this many.
+pub const MAX_LOG_ENTRIES: usize = 500;
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum LogCategory {
+    Combat,
+    Item,
+    System,
+}
+
+impl LogCategory {
+    /// Colour of the plain text in an entry of this category.
+    pub fn color(self) -> RGB {
+        match self {
+            LogCategory::Combat => RGB::named(rltk::WHITE),
+            LogCategory::Item => RGB::named(rltk::CYAN),
+            LogCategory::System => RGB::named(rltk::GREY),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LogSegment {
+    pub text: String,
+    pub color: RGB,
+}
+
+/// One line of the log, built up from coloured segments:
+///
+/// `LogEntry::new(LogCategory::Combat).colored("Orc", fg).text(" hits you for ").damage(3)`
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub category: LogCategory,
+    pub segments: Vec<LogSegment>,
+    /// How many times in a row this message was logged.
+    pub count: u32,
+}
+
+impl LogEntry {
+    pub fn new(category: LogCategory) -> LogEntry {
+        LogEntry {
+            category,
+            segments: Vec::new(),
+            count: 1,
+        }
+    }
+
+    pub fn text<S: ToString>(self, text: S) -> LogEntry {
+        let color = self.category.color();
+        self.colored(text, color)
+    }
+
+    pub fn colored<S: ToString>(mut self, text: S, color: RGB) -> LogEntry {
+        self.segments.push(LogSegment {
+            text: text.to_string(),
+            color,
+        });
+        self
+    }
+
+    /// An entity's name, in the colour it is drawn with on the map.
+    pub fn name(self, name: &str, renderable: Option<&Renderable>) -> LogEntry {
+        let color = renderable.map_or(RGB::named(rltk::WHITE), |render| render.fg);
+        self.colored(name, color)
+    }
+
+    pub fn damage(self, amount: i32) -> LogEntry {
+        self.colored(amount, RGB::named(rltk::RED))
+    }
+
+    pub fn full_text(&self) -> String {
+        self.segments.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    fn same_message(&self, other: &LogEntry) -> bool {
+        self.category == other.category && self.full_text() == other.full_text()
+    }
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct GameLog {
-    pub entries: Vec<String>,
+    entries: VecDeque<LogEntry>,
+    #[serde(default)]
+    logged: usize,
+}
+
+impl GameLog {
+    /// Adds an entry, or bumps the count of the newest one if it is the same message.
+    pub fn log(&mut self, entry: LogEntry) {
+        self.logged += 1;
+        if let Some(last) = self.entries.back_mut() {
+            if last.same_message(&entry) {
+                last.count += 1;
+                return;
+            }
+        }
+
+        self.entries.push_back(entry);
+        while self.entries.len() > MAX_LOG_ENTRIES {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Shorthand for a one-segment system message.
+    pub fn system<S: ToString>(&mut self, text: S) {
+        self.log(LogEntry::new(LogCategory::System).text(text));
+    }
+
+    /// Oldest entry first.
+    pub fn entries(&self) -> &VecDeque<LogEntry> {
+        &self.entries
+    }
+
+    /// How many messages were ever logged, repeats included. Tells whether
+    /// anything new came up even once old entries are dropped.
+    pub fn logged(&self) -> usize {
+        self.logged
+    }
 }
@@ -1,7 +1,12 @@
-use crate::GameLog;
+use crate::{
+    load_raws, screen_to_world, world_to_screen, xp_to_next_level, Asleep, Confusion, Equipped,
+    Experience, GameLog, Hidden, HungerClock, HungerState, InInventory, LevelUpBanner, LogEntry,
+    Map, Name, Poisoned, Viewshed, VIEW_HEIGHT, VIEW_WIDTH,
+};
 
-use super::{CombatStats, Player};
-use rltk::{Rltk, RGB};
+use super::{CombatStats, Player, RunStats};
+use rltk::{Point, Rltk, VirtualKeyCode, RGB};
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 use specs::{World, WorldExt};
 
@@ -18,8 +23,22 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     let combat_stats = ecs.read_storage::<CombatStats>();
     let players = ecs.read_storage::<Player>();
     let log = ecs.fetch::<GameLog>();
+    let map = ecs.fetch::<Map>();
 
-    for (_player, stats) in (&players, &combat_stats).join() {
+    let depth = format!("Depth: {}", map.depth);
+    ctx.print_color(
+        2,
+        43,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        &depth,
+    );
+
+    let confused = ecs.read_storage::<Confusion>();
+    let poisoned = ecs.read_storage::<Poisoned>();
+    let asleep = ecs.read_storage::<Asleep>();
+
+    for (entity, _player, stats) in (&ecs.entities(), &players, &combat_stats).join() {
         let health = format!(" HP: {} / {} ", stats.hp, stats.max_hp);
         ctx.print_color(
             12,
@@ -32,19 +51,722 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         ctx.draw_bar_horizontal(
             28,
             43,
-            51,
+            30,
             stats.hp,
             stats.max_hp,
             RGB::named(rltk::RED),
             RGB::named(rltk::BLACK),
         );
+
+        // Status effects and their remaining turns, right of the HP bar.
+        let mut effects: Vec<(String, RGB)> = Vec::new();
+        if let Some(confusion) = confused.get(entity) {
+            effects.push((format!("Conf {}", confusion.turns), RGB::named(rltk::PINK)));
+        }
+        if let Some(poison) = poisoned.get(entity) {
+            effects.push((format!("Pois {}", poison.turns), RGB::named(rltk::GREEN)));
+        }
+        if let Some(sleep) = asleep.get(entity) {
+            effects.push((format!("Sleep {}", sleep.turns), RGB::named(rltk::CYAN)));
+        }
+
+        let mut x = 59;
+        for (effect, color) in effects.iter() {
+            ctx.print_color(x, 43, *color, RGB::named(rltk::BLACK), effect);
+            x += effect.len() as i32 + 1;
+        }
+    }
+
+    // Level and progress towards the next one, on the bottom border.
+    let experience = ecs.read_storage::<Experience>();
+    for (_player, experience) in (&players, &experience).join() {
+        let needed = xp_to_next_level(experience.level);
+        let level = format!(
+            " Level {} XP: {} / {} ",
+            experience.level, experience.xp, needed
+        );
+        ctx.print_color(
+            2,
+            49,
+            RGB::named(rltk::GOLD),
+            RGB::named(rltk::BLACK),
+            &level,
+        );
+
+        ctx.draw_bar_horizontal(
+            level.len() as i32 + 3,
+            49,
+            30,
+            experience.xp,
+            needed,
+            RGB::named(rltk::GOLD),
+            RGB::named(rltk::BLACK),
+        );
+    }
+
+    // Hunger goes in the bottom right corner, and only when it matters.
+    let hunger_clocks = ecs.read_storage::<HungerClock>();
+    for (_player, clock) in (&players, &hunger_clocks).join() {
+        let (hunger, color) = match clock.state {
+            HungerState::WellFed => (" Well Fed ", RGB::named(rltk::GREEN)),
+            HungerState::Normal => continue,
+            HungerState::Hungry => (" Hungry ", RGB::named(rltk::ORANGE)),
+            HungerState::Starving => (" Starving ", RGB::named(rltk::RED)),
+        };
+        ctx.print_color(
+            78 - hunger.len() as i32,
+            49,
+            color,
+            RGB::named(rltk::BLACK),
+            hunger,
+        );
+    }
+
+    // Equipped gear is listed in the top right corner of the map.
+    let player_entity = ecs.fetch::<Entity>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let names = ecs.read_storage::<Name>();
+    let mut gear_y = 1;
+    for (equipped_by, name) in (&equipped, &names).join() {
+        if equipped_by.owner == *player_entity {
+            let gear = format!("{:?}: {}", equipped_by.slot, name.name);
+            ctx.print_color(
+                78 - gear.len() as i32,
+                gear_y,
+                RGB::named(rltk::CYAN),
+                RGB::named(rltk::BLACK),
+                &gear,
+            );
+            gear_y += 1;
+        }
+    }
+
+    let banner = ecs.fetch::<LevelUpBanner>();
+    if ecs.fetch::<RunStats>().turns < banner.until_turn {
+        ctx.print_color_centered(
+            5,
+            RGB::named(rltk::GOLD),
+            RGB::named(rltk::BLACK),
+            format!("*** You reached level {}! ***", banner.level),
+        );
+    }
+
+    for (row, entry) in log.entries().iter().rev().take(5).enumerate() {
+        print_log_entry(ctx, 2, 44 + row as i32, 76, entry);
+    }
+
+    draw_tooltips(ecs, ctx);
+}
+
+/// Lists what is under the mouse cursor, with the health of creatures.
+fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
+    let map = ecs.fetch::<Map>();
+    let names = ecs.read_storage::<Name>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let hidden = ecs.read_storage::<Hidden>();
+
+    let (mouse_x, mouse_y) = ctx.mouse_pos();
+    if !(0..VIEW_WIDTH).contains(&mouse_x) || !(0..VIEW_HEIGHT).contains(&mouse_y) {
+        return;
+    }
+    let world = screen_to_world(ecs, Point::new(mouse_x, mouse_y));
+    if world.x < 0 || world.x >= map.width || world.y < 0 || world.y >= map.height {
+        return;
+    }
+    let index = map.map_index(world.x, world.y);
+    if !map.visible_tiles[index] {
+        return;
+    }
+
+    let tooltip: Vec<String> = map.tile_content[index]
+        .iter()
+        .filter(|entity| hidden.get(**entity).is_none())
+        .filter_map(|entity| {
+            let name = names.get(*entity)?;
+            Some(match combat_stats.get(*entity) {
+                Some(stats) => format!("{} ({}/{} HP)", name.name, stats.hp, stats.max_hp),
+                None => name.name.clone(),
+            })
+        })
+        .collect();
+    if tooltip.is_empty() {
+        return;
+    }
+
+    let width = tooltip.iter().map(|line| line.len()).max().unwrap_or(0) as i32 + 3;
+    let height = tooltip.len() as i32 + 1;
+
+    // The box sits right of the cursor, unless it would run off the screen.
+    let box_x = if mouse_x + 1 + width > VIEW_WIDTH - 1 {
+        mouse_x - 1 - width
+    } else {
+        mouse_x + 1
+    };
+    let box_y = i32::max(0, i32::min(mouse_y, VIEW_HEIGHT - 1 - height));
+
+    ctx.draw_box(
+        box_x,
+        box_y,
+        width,
+        height,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    for (row, line) in tooltip.iter().enumerate() {
+        ctx.print_color(
+            box_x + 2,
+            box_y + 1 + row as i32,
+            RGB::named(rltk::WHITE),
+            RGB::named(rltk::BLACK),
+            line,
+        );
+    }
+    ctx.set_bg(mouse_x, mouse_y, RGB::named(rltk::MAGENTA));
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum ItemMenuResult {
+    Cancel,
+    NoResponse,
+    Selected,
+}
+
+pub fn show_inventory(ecs: &World, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    item_menu(ecs, ctx, "Inventory")
+}
+
+pub fn drop_item_menu(ecs: &World, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    item_menu(ecs, ctx, "Drop Which Item?")
+}
+
+pub fn destroy_item_menu(ecs: &World, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    item_menu(ecs, ctx, "Destroy Which Item?")
+}
+
+/// Lists the player's backpack, each item behind a letter shortcut.
+fn item_menu(ecs: &World, ctx: &mut Rltk, title: &str) -> (ItemMenuResult, Option<Entity>) {
+    let player_entity = ecs.fetch::<Entity>();
+    let names = ecs.read_storage::<Name>();
+    let backpack = ecs.read_storage::<InInventory>();
+    let entities = ecs.entities();
+
+    let inventory: Vec<(Entity, &Name)> = (&entities, &backpack, &names)
+        .join()
+        .filter(|(_entity, item, _name)| item.owner == *player_entity)
+        .map(|(entity, _item, name)| (entity, name))
+        .collect();
+
+    let count = inventory.len() as i32;
+    let y = 25 - (count / 2);
+    ctx.draw_box(
+        15,
+        y - 2,
+        31,
+        count + 3,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        18,
+        y - 2,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        title,
+    );
+    ctx.print_color(
+        18,
+        y + count + 1,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "ESCAPE to cancel",
+    );
+
+    for (row, (_entity, name)) in inventory.iter().enumerate() {
+        let line = y + row as i32;
+        ctx.set(
+            17,
+            line,
+            RGB::named(rltk::WHITE),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437('('),
+        );
+        ctx.set(
+            18,
+            line,
+            RGB::named(rltk::YELLOW),
+            RGB::named(rltk::BLACK),
+            97 + row as rltk::FontCharType,
+        );
+        ctx.set(
+            19,
+            line,
+            RGB::named(rltk::WHITE),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437(')'),
+        );
+
+        ctx.print(21, line, &name.name);
+    }
+
+    match ctx.key {
+        None => (ItemMenuResult::NoResponse, None),
+        Some(VirtualKeyCode::Escape) => (ItemMenuResult::Cancel, None),
+        Some(key) => {
+            let selection = rltk::letter_to_option(key);
+            if selection > -1 && selection < count {
+                return (
+                    ItemMenuResult::Selected,
+                    Some(inventory[selection as usize].0),
+                );
+            }
+            (ItemMenuResult::NoResponse, None)
+        }
+    }
+}
+
+/// Highlights the tiles the player can see within `range` and lets them pick one,
+/// either by clicking it or by moving `cursor` with the movement keys and pressing ENTER.
+pub fn ranged_target(
+    ecs: &World,
+    ctx: &mut Rltk,
+    range: i32,
+    cursor: &mut Point,
+) -> (ItemMenuResult, Option<Point>) {
+    let player_entity = ecs.fetch::<Entity>();
+    let player_pos = ecs.fetch::<Point>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+
+    ctx.print_color(
+        5,
+        0,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Select Target:",
+    );
+
+    let mut available_cells: Vec<Point> = Vec::new();
+    if let Some(visible) = viewsheds.get(*player_entity) {
+        for tile in visible.visible_tiles.iter() {
+            let distance = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *tile);
+            if distance <= range as f32 {
+                if let Some(screen) = world_to_screen(ecs, *tile) {
+                    ctx.set_bg(screen.x, screen.y, RGB::named(rltk::BLUE));
+                }
+                available_cells.push(*tile);
+            }
+        }
+    } else {
+        return (ItemMenuResult::Cancel, None);
+    }
+
+    // The cursor lives in world coordinates, the mouse on the screen.
+    let mouse_pos = ctx.mouse_pos();
+    let mouse_point = screen_to_world(ecs, Point::new(mouse_pos.0, mouse_pos.1));
+    let mouse_valid = available_cells.contains(&mouse_point);
+    if mouse_valid {
+        ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::CYAN));
+        if ctx.left_click {
+            return (ItemMenuResult::Selected, Some(mouse_point));
+        }
+    } else {
+        ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::RED));
+        if ctx.left_click {
+            return (ItemMenuResult::Cancel, None);
+        }
+    }
+
+    let cursor_valid = available_cells.contains(cursor);
+    if let Some(screen) = world_to_screen(ecs, *cursor) {
+        let color = if cursor_valid { rltk::CYAN } else { rltk::RED };
+        ctx.set_bg(screen.x, screen.y, RGB::named(color));
+    }
+
+    let (delta_x, delta_y) = match ctx.key {
+        None => return (ItemMenuResult::NoResponse, None),
+        Some(key) => match key {
+            VirtualKeyCode::Escape => return (ItemMenuResult::Cancel, None),
+            VirtualKeyCode::Return if cursor_valid => {
+                return (ItemMenuResult::Selected, Some(*cursor))
+            }
+            VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => (-1, 0),
+            VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => (1, 0),
+            VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => (0, -1),
+            VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => (0, 1),
+            _ => return (ItemMenuResult::NoResponse, None),
+        },
+    };
+
+    // Only move the cursor onto tiles that can be targeted.
+    let moved = Point::new(cursor.x + delta_x, cursor.y + delta_y);
+    if available_cells.contains(&moved) {
+        *cursor = moved;
+    }
+
+    (ItemMenuResult::NoResponse, None)
+}
+
+/// Prints an entry segment by segment, cut off after `width` characters.
+/// Repeats of the same message are shown as a count.
+fn print_log_entry(ctx: &mut Rltk, x: i32, y: i32, width: usize, entry: &LogEntry) {
+    let mut column = 0;
+    let repeats = format!(" x{}", entry.count);
+    let mut segments: Vec<(&str, RGB)> = entry
+        .segments
+        .iter()
+        .map(|segment| (segment.text.as_str(), segment.color))
+        .collect();
+    if entry.count > 1 {
+        segments.push((&repeats, RGB::named(rltk::YELLOW)));
     }
 
-    let mut y = 44;
-    for entry in log.entries.iter().rev() {
-        if y < 49 {
-            ctx.print(2, y, entry);
+    for (text, color) in segments {
+        let text: String = text.chars().take(width - column).collect();
+        ctx.print_color(x + column as i32, y, color, RGB::named(rltk::BLACK), &text);
+        column += text.chars().count();
+        if column >= width {
+            break;
         }
-        y += 1;
+    }
+}
+
+pub enum LogViewResult {
+    Close,
+    Scroll { offset: usize },
+}
+
+/// Full-screen message history. `offset` is how many entries the view is
+/// scrolled back from the newest one.
+pub fn show_log(ecs: &World, ctx: &mut Rltk, offset: usize) -> LogViewResult {
+    const PAGE: usize = 46;
+
+    let log = ecs.fetch::<GameLog>();
+    let entries = log.entries();
+    let max_offset = entries.len().saturating_sub(PAGE);
+    let offset = usize::min(offset, max_offset);
+
+    ctx.draw_box(
+        0,
+        0,
+        79,
+        49,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        3,
+        0,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Message log",
+    );
+    ctx.print_color(
+        3,
+        49,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "PGUP/PGDN or UP/DOWN to scroll, ESCAPE to close",
+    );
+
+    // Newest entry at the bottom, like the log panel.
+    let end = entries.len() - offset;
+    let start = end.saturating_sub(PAGE);
+    for (row, entry) in entries.range(start..end).enumerate() {
+        print_log_entry(ctx, 2, 2 + row as i32, 76, entry);
+    }
+
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) | Some(VirtualKeyCode::M) => LogViewResult::Close,
+        Some(VirtualKeyCode::PageUp) => LogViewResult::Scroll {
+            offset: usize::min(offset + PAGE, max_offset),
+        },
+        Some(VirtualKeyCode::PageDown) => LogViewResult::Scroll {
+            offset: offset.saturating_sub(PAGE),
+        },
+        Some(VirtualKeyCode::Up) => LogViewResult::Scroll {
+            offset: usize::min(offset + 1, max_offset),
+        },
+        Some(VirtualKeyCode::Down) => LogViewResult::Scroll {
+            offset: offset.saturating_sub(1),
+        },
+        _ => LogViewResult::Scroll { offset },
+    }
+}
+
+pub enum SpawnTableViewResult {
+    Close,
+    Depth { depth: i32 },
+}
+
+/// Debug view of the spawn tables at `depth`, with the chance of each entry
+/// being picked. LEFT/RIGHT look at shallower or deeper levels.
+pub fn show_spawn_tables(ctx: &mut Rltk, depth: i32) -> SpawnTableViewResult {
+    let raws = load_raws();
+
+    ctx.draw_box(
+        0,
+        0,
+        79,
+        49,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        3,
+        0,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        format!("Spawn tables at depth {}", depth),
+    );
+    ctx.print_color(
+        3,
+        49,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "LEFT/RIGHT to change depth, ESCAPE to close",
+    );
+
+    // Traps go under the monsters, which never fill the column.
+    let monsters = raws.monster_spawn_table(depth);
+    let traps_y = 6 + monsters.entries().len() as i32;
+    let tables = [
+        ("Monsters", monsters, 2, 2),
+        ("Items", raws.item_spawn_table(depth), 41, 2),
+        ("Traps", raws.trap_spawn_table(depth), 2, traps_y),
+    ];
+    for (title, table, x, y) in tables.iter() {
+        ctx.print_color(
+            *x,
+            *y,
+            RGB::named(rltk::CYAN),
+            RGB::named(rltk::BLACK),
+            title,
+        );
+        for (row, entry) in table.entries().iter().enumerate() {
+            let chance = entry.weight as f32 * 100.0 / table.total_weight() as f32;
+            ctx.print(
+                *x,
+                *y + 2 + row as i32,
+                format!("{:<22}{:>4}{:>7.1}%", entry.name, entry.weight, chance),
+            );
+        }
+    }
+
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) | Some(VirtualKeyCode::F1) => SpawnTableViewResult::Close,
+        Some(VirtualKeyCode::Left) => SpawnTableViewResult::Depth {
+            depth: i32::max(depth - 1, 1),
+        },
+        Some(VirtualKeyCode::Right) => SpawnTableViewResult::Depth { depth: depth + 1 },
+        _ => SpawnTableViewResult::Depth { depth },
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum GameOverResult {
+    NoSelection,
+    QuitToMenu,
+}
+
+pub fn game_over(ecs: &World, ctx: &mut Rltk) -> GameOverResult {
+    let run_stats = ecs.fetch::<RunStats>();
+    let map = ecs.fetch::<Map>();
+    let player_entity = ecs.fetch::<Entity>();
+    let players = ecs.read_storage::<Player>();
+    let moves = players
+        .get(*player_entity)
+        .map_or(0, |player| player.number_of_moves);
+
+    ctx.print_color_centered(
+        15,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Your journey has ended!",
+    );
+
+    let cause = if run_stats.last_hurt_by.is_empty() {
+        "Killed by unknown causes".to_string()
+    } else {
+        format!("Killed by: {}", run_stats.last_hurt_by)
+    };
+    ctx.print_color_centered(17, RGB::named(rltk::RED), RGB::named(rltk::BLACK), cause);
+    ctx.print_color_centered(
+        19,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+        format!("Depth reached: {}", map.depth),
+    );
+    ctx.print_color_centered(
+        20,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+        format!("Monsters killed: {}", run_stats.kills),
+    );
+    ctx.print_color_centered(
+        21,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+        format!("Moves: {}", moves),
+    );
+
+    ctx.print_color_centered(
+        24,
+        RGB::named(rltk::MAGENTA),
+        RGB::named(rltk::BLACK),
+        "Press ENTER to return to the main menu.",
+    );
+
+    match ctx.key {
+        Some(VirtualKeyCode::Return) | Some(VirtualKeyCode::Escape) => GameOverResult::QuitToMenu,
+        _ => GameOverResult::NoSelection,
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub enum MainMenuSelection {
+    NewGame,
+    LoadGame,
+    Options,
+    Quit,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum MainMenuResult {
+    NoSelection { selected: MainMenuSelection },
+    Selected { selected: MainMenuSelection },
+}
+
+const MAIN_MENU_ENTRIES: [(MainMenuSelection, &str); 4] = [
+    (MainMenuSelection::NewGame, "New Game"),
+    (MainMenuSelection::LoadGame, "Load Game"),
+    (MainMenuSelection::Options, "Options"),
+    (MainMenuSelection::Quit, "Quit"),
+];
+
+fn is_menu_entry_enabled(entry: MainMenuSelection, save_exists: bool) -> bool {
+    entry != MainMenuSelection::LoadGame || save_exists
+}
+
+// Next enabled entry in the direction of `step`, wrapping around the ends.
+fn step_main_menu(selection: MainMenuSelection, step: i32, save_exists: bool) -> MainMenuSelection {
+    let count = MAIN_MENU_ENTRIES.len() as i32;
+    let mut index = MAIN_MENU_ENTRIES
+        .iter()
+        .position(|(entry, _)| *entry == selection)
+        .unwrap_or(0) as i32;
+
+    loop {
+        index = (index + step + count) % count;
+        let entry = MAIN_MENU_ENTRIES[index as usize].0;
+        if is_menu_entry_enabled(entry, save_exists) {
+            return entry;
+        }
+    }
+}
+
+pub fn main_menu(
+    ctx: &mut Rltk,
+    selection: MainMenuSelection,
+    save_exists: bool,
+) -> MainMenuResult {
+    ctx.print_color_centered(
+        15,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Roguelike",
+    );
+
+    // Hovering an entry with the mouse selects it, clicking picks it.
+    let mouse_pos = ctx.mouse_pos();
+    let mut selection = selection;
+    for (row, (entry, label)) in MAIN_MENU_ENTRIES.iter().enumerate() {
+        let y = 24 + row as i32;
+        let half_width = label.len() as i32 / 2;
+        let enabled = is_menu_entry_enabled(*entry, save_exists);
+
+        if enabled && mouse_pos.1 == y && (mouse_pos.0 - 40).abs() <= half_width {
+            if ctx.left_click {
+                return MainMenuResult::Selected { selected: *entry };
+            }
+            selection = *entry;
+        }
+
+        let fg = if !enabled {
+            RGB::named(rltk::GREY)
+        } else if *entry == selection {
+            RGB::named(rltk::MAGENTA)
+        } else {
+            RGB::named(rltk::WHITE)
+        };
+        ctx.print_color_centered(y, fg, RGB::named(rltk::BLACK), label);
+    }
+
+    match ctx.key {
+        None => MainMenuResult::NoSelection {
+            selected: selection,
+        },
+        Some(key) => match key {
+            VirtualKeyCode::Escape => MainMenuResult::NoSelection {
+                selected: MainMenuSelection::Quit,
+            },
+            VirtualKeyCode::Up | VirtualKeyCode::K => MainMenuResult::NoSelection {
+                selected: step_main_menu(selection, -1, save_exists),
+            },
+            VirtualKeyCode::Down | VirtualKeyCode::J => MainMenuResult::NoSelection {
+                selected: step_main_menu(selection, 1, save_exists),
+            },
+            VirtualKeyCode::Return => MainMenuResult::Selected {
+                selected: selection,
+            },
+            _ => MainMenuResult::NoSelection {
+                selected: selection,
+            },
+        },
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum OptionsMenuResult {
+    NoSelection,
+    ToggleScanlines,
+    Back,
+}
+
+pub fn options_menu(ctx: &mut Rltk, scanlines: bool) -> OptionsMenuResult {
+    ctx.print_color_centered(
+        15,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Options",
+    );
+
+    let label = if scanlines {
+        "Scanlines: On"
+    } else {
+        "Scanlines: Off"
+    };
+    let mouse_pos = ctx.mouse_pos();
+    let hovered = mouse_pos.1 == 24 && (mouse_pos.0 - 40).abs() <= label.len() as i32 / 2;
+
+    ctx.print_color_centered(
+        24,
+        RGB::named(rltk::MAGENTA),
+        RGB::named(rltk::BLACK),
+        label,
+    );
+    ctx.print_color_centered(
+        27,
+        RGB::named(rltk::GREY),
+        RGB::named(rltk::BLACK),
+        "ENTER to toggle, ESCAPE to go back",
+    );
+
+    if hovered && ctx.left_click {
+        return OptionsMenuResult::ToggleScanlines;
+    }
+
+    match ctx.key {
+        Some(VirtualKeyCode::Return) => OptionsMenuResult::ToggleScanlines,
+        Some(VirtualKeyCode::Escape) => OptionsMenuResult::Back,
+        _ => OptionsMenuResult::NoSelection,
     }
 }
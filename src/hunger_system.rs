@@ -0,0 +1,93 @@
+use crate::{
+    GameLog, HungerClock, HungerState, LogCategory, LogEntry, MyTurn, RunState, RunStats,
+    SufferDamage,
+};
+
+use specs::prelude::*;
+
+/// How many turns each hunger state lasts before the next one sets in.
+pub const WELL_FED_TURNS: i32 = 20;
+const NORMAL_TURNS: i32 = 200;
+const HUNGRY_TURNS: i32 = 200;
+/// Damage taken every turn spent starving.
+const STARVING_DAMAGE: i32 = 1;
+
+/// Winds down the hunger clock of everyone taking a turn. Once starving,
+/// every turn hurts until something is eaten.
+pub struct HungerSystem {}
+
+impl<'a> System<'a> for HungerSystem {
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, RunState>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RunStats>,
+        Entities<'a>,
+        WriteStorage<'a, HungerClock>,
+        ReadStorage<'a, MyTurn>,
+        WriteStorage<'a, SufferDamage>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            runstate,
+            mut gamelog,
+            mut run_stats,
+            entities,
+            mut hunger_clocks,
+            turns,
+            mut suffer_damage,
+        ) = data;
+
+        if *runstate != RunState::Ticking {
+            return;
+        }
+
+        for (entity, clock, _turn) in (&entities, &mut hunger_clocks, &turns).join() {
+            let is_player = entity == *player_entity;
+
+            clock.duration -= 1;
+            if clock.duration > 0 {
+                continue;
+            }
+
+            match clock.state {
+                HungerState::WellFed => {
+                    clock.state = HungerState::Normal;
+                    clock.duration = NORMAL_TURNS;
+                    if is_player {
+                        gamelog.system("You are no longer well fed.");
+                    }
+                }
+                HungerState::Normal => {
+                    clock.state = HungerState::Hungry;
+                    clock.duration = HUNGRY_TURNS;
+                    if is_player {
+                        gamelog.system("You are hungry.");
+                    }
+                }
+                HungerState::Hungry => {
+                    clock.state = HungerState::Starving;
+                    clock.duration = 0;
+                    if is_player {
+                        gamelog.system("You are starving!");
+                    }
+                }
+                HungerState::Starving => {
+                    // Starvation has no attacker to credit.
+                    SufferDamage::new_damage(&mut suffer_damage, entity, STARVING_DAMAGE, None);
+                    if is_player {
+                        run_stats.last_hurt_by = "Starvation".to_string();
+                        gamelog.log(
+                            LogEntry::new(LogCategory::Combat)
+                                .text("Your hunger pangs are getting painful! You suffer ")
+                                .damage(STARVING_DAMAGE)
+                                .text(" hp damage."),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
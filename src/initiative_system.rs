@@ -0,0 +1,51 @@
+use crate::{Initiative, MyTurn, RunState};
+
+use specs::prelude::*;
+
+/// Energy an entity spends to take a turn.
+pub const TURN_ENERGY: i32 = 100;
+/// Speed of the player and of most monsters: one turn every ten ticks.
+pub const NORMAL_SPEED: i32 = 10;
+
+/// Hands out turns while the game is ticking. Every tick, entities gain
+/// energy equal to their speed, and the ones that reach `TURN_ENERGY` spend
+/// it and get `MyTurn`. Ticks go by until at least one entity can act.
+pub struct InitiativeSystem {}
+
+impl<'a> System<'a> for InitiativeSystem {
+    type SystemData = (
+        ReadExpect<'a, RunState>,
+        Entities<'a>,
+        WriteStorage<'a, Initiative>,
+        WriteStorage<'a, MyTurn>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (runstate, entities, mut initiatives, mut turns) = data;
+
+        if *runstate != RunState::Ticking {
+            return;
+        }
+
+        // Turns from the previous tick have been taken by now.
+        turns.clear();
+
+        if !(&initiatives).join().any(|initiative| initiative.speed > 0) {
+            return;
+        }
+
+        let mut anyone_acts = false;
+        while !anyone_acts {
+            for (entity, initiative) in (&entities, &mut initiatives).join() {
+                initiative.energy += initiative.speed;
+                if initiative.energy >= TURN_ENERGY {
+                    initiative.energy -= TURN_ENERGY;
+                    anyone_acts = true;
+                    turns
+                        .insert(entity, MyTurn {})
+                        .expect("Unable to insert turn");
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,12 @@
-use super::{gamelog::GameLog, InInventory, Name, Position, WantsToPickupItem};
+use super::{
+    gamelog::{GameLog, LogCategory, LogEntry},
+    AreaOfEffect, Asleep, CombatStats, Confusion, Consumable, Equippable, Equipped, HungerClock,
+    HungerState, InInventory, InflictsDamage, Map, Name, Poisoned, Poisoner, Position, Potion,
+    ProvidesFood, Renderable, SufferDamage, WantsToDestroyItem, WantsToDropItem, WantsToPickupItem,
+    WantsToUseItem, WELL_FED_TURNS,
+};
+use crate::RunStats;
+use rltk::{Point, RGB};
 use specs::prelude::*;
 
 pub struct ItemCollectionSystem {}
@@ -14,17 +22,374 @@ impl<'a> System<'a> for ItemCollectionSystem {
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, mut wants_pickup, mut positions, names, mut backpack) = data;
+        let (player_entity, mut gamelog, mut wants_pickup, mut positions, names, mut backpack) =
+            data;
 
         for pickup in wants_pickup.join() {
             positions.remove(pickup.item);
-            backpack.insert(pickup.item, InInventory{owner: pickup.collected_by}).expect("Unable to insert backpack item.");
+            backpack
+                .insert(
+                    pickup.item,
+                    InInventory {
+                        owner: pickup.collected_by,
+                    },
+                )
+                .expect("Unable to insert backpack item.");
 
             if pickup.collected_by == *player_entity {
-                gamelog.entries.push(format!("You pick up the {}.", names.get(pickup.item).unwrap().name))
+                gamelog.log(LogEntry::new(LogCategory::Item).text(format!(
+                    "You pick up the {}.",
+                    names.get(pickup.item).unwrap().name
+                )));
             }
         }
 
         wants_pickup.clear();
     }
 }
+
+pub struct ItemUseSystem {}
+
+impl<'a> System<'a> for ItemUseSystem {
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToUseItem>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Potion>,
+        WriteStorage<'a, CombatStats>,
+        ReadStorage<'a, Equippable>,
+        WriteStorage<'a, Equipped>,
+        WriteStorage<'a, InInventory>,
+        ReadExpect<'a, Map>,
+        ReadStorage<'a, Consumable>,
+        ReadStorage<'a, InflictsDamage>,
+        ReadStorage<'a, AreaOfEffect>,
+        WriteStorage<'a, SufferDamage>,
+        WriteStorage<'a, Confusion>,
+        WriteStorage<'a, Poisoned>,
+        WriteStorage<'a, Asleep>,
+        WriteExpect<'a, RunStats>,
+        ReadStorage<'a, Renderable>,
+        ReadStorage<'a, ProvidesFood>,
+        WriteStorage<'a, HungerClock>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            entities,
+            mut wants_use,
+            names,
+            potions,
+            mut combat_stats,
+            equippable,
+            mut equipped,
+            mut backpack,
+            map,
+            consumables,
+            inflict_damage,
+            aoe,
+            mut suffer_damage,
+            mut confused,
+            mut poisoned,
+            mut asleep,
+            mut run_stats,
+            renderables,
+            provides_food,
+            mut hunger_clocks,
+        ) = data;
+
+        for (entity, use_item) in (&entities, &wants_use).join() {
+            let mut used_item = false;
+
+            if let (Some(potion), Some(stats)) =
+                (potions.get(use_item.item), combat_stats.get_mut(entity))
+            {
+                let healed = i32::min(potion.heal_amount, stats.max_hp - stats.hp);
+                stats.hp += healed;
+                used_item = true;
+
+                if entity == *player_entity {
+                    gamelog.log(
+                        LogEntry::new(LogCategory::Item)
+                            .text(format!(
+                                "You drink the {}, healing ",
+                                names.get(use_item.item).unwrap().name
+                            ))
+                            .colored(healed, RGB::named(rltk::GREEN))
+                            .text(" hp."),
+                    );
+                }
+            }
+
+            if let (Some(_food), Some(clock)) = (
+                provides_food.get(use_item.item),
+                hunger_clocks.get_mut(entity),
+            ) {
+                clock.state = HungerState::WellFed;
+                clock.duration = WELL_FED_TURNS;
+                used_item = true;
+
+                if entity == *player_entity {
+                    gamelog.log(LogEntry::new(LogCategory::Item).text(format!(
+                        "You eat the {}.",
+                        names.get(use_item.item).unwrap().name
+                    )));
+                }
+            }
+
+            // Creatures caught by a targeted item. An area of effect hits everything
+            // the blast can see from the impact point, so walls shelter whoever is behind them.
+            let mut targets: Vec<Entity> = Vec::new();
+            if let Some(target) = use_item.target {
+                let mut blast_tiles: Vec<Point> = vec![target];
+                if let Some(area_effect) = aoe.get(use_item.item) {
+                    blast_tiles = rltk::field_of_view(target, area_effect.radius, &*map);
+                    blast_tiles.retain(|p| {
+                        p.x > 0 && p.x < map.width - 1 && p.y > 0 && p.y < map.height - 1
+                    });
+                }
+
+                for tile in blast_tiles.iter() {
+                    let index = map.map_index(tile.x, tile.y);
+                    for victim in map.tile_content[index].iter() {
+                        if combat_stats.get(*victim).is_some() {
+                            targets.push(*victim);
+                        }
+                    }
+                }
+            }
+
+            if let Some(damage) = inflict_damage.get(use_item.item) {
+                for victim in targets.iter() {
+                    SufferDamage::new_damage(
+                        &mut suffer_damage,
+                        *victim,
+                        damage.damage,
+                        Some(entity),
+                    );
+                    if *victim == *player_entity {
+                        run_stats.last_hurt_by = names.get(use_item.item).unwrap().name.clone();
+                    }
+                    if entity == *player_entity {
+                        gamelog.log(
+                            LogEntry::new(LogCategory::Combat)
+                                .text(format!(
+                                    "You use {} on ",
+                                    names.get(use_item.item).unwrap().name
+                                ))
+                                .name(&names.get(*victim).unwrap().name, renderables.get(*victim))
+                                .text(", inflicting ")
+                                .damage(damage.damage)
+                                .text(" hp."),
+                        );
+                    }
+                }
+
+                used_item = true;
+            }
+
+            if let Some(turns) = confused.get(use_item.item).map(|c| c.turns) {
+                for victim in targets.iter() {
+                    confused
+                        .insert(*victim, Confusion { turns })
+                        .expect("Unable to insert status");
+                    if entity == *player_entity {
+                        gamelog.log(
+                            LogEntry::new(LogCategory::Combat)
+                                .text(format!(
+                                    "You use {} on ",
+                                    names.get(use_item.item).unwrap().name
+                                ))
+                                .name(&names.get(*victim).unwrap().name, renderables.get(*victim))
+                                .text(", confusing them."),
+                        );
+                    }
+                }
+
+                used_item = true;
+            }
+
+            if let Some((dmg_per_turn, turns)) = poisoned
+                .get(use_item.item)
+                .map(|p| (p.dmg_per_turn, p.turns))
+            {
+                for victim in targets.iter() {
+                    poisoned
+                        .insert(
+                            *victim,
+                            Poisoned {
+                                dmg_per_turn,
+                                turns,
+                                source: Poisoner(Some(entity)),
+                            },
+                        )
+                        .expect("Unable to insert status");
+                    if entity == *player_entity {
+                        gamelog.log(
+                            LogEntry::new(LogCategory::Combat)
+                                .text(format!(
+                                    "You use {} on ",
+                                    names.get(use_item.item).unwrap().name
+                                ))
+                                .name(&names.get(*victim).unwrap().name, renderables.get(*victim))
+                                .text(", poisoning them."),
+                        );
+                    }
+                }
+
+                used_item = true;
+            }
+
+            if let Some(turns) = asleep.get(use_item.item).map(|a| a.turns) {
+                for victim in targets.iter() {
+                    asleep
+                        .insert(*victim, Asleep { turns })
+                        .expect("Unable to insert status");
+                    if entity == *player_entity {
+                        gamelog.log(
+                            LogEntry::new(LogCategory::Combat)
+                                .text(format!(
+                                    "You use {} on ",
+                                    names.get(use_item.item).unwrap().name
+                                ))
+                                .name(&names.get(*victim).unwrap().name, renderables.get(*victim))
+                                .text(", putting them to sleep."),
+                        );
+                    }
+                }
+
+                used_item = true;
+            }
+
+            if used_item && consumables.get(use_item.item).is_some() {
+                entities.delete(use_item.item).expect("Delete failed");
+            }
+
+            if let Some(can_equip) = equippable.get(use_item.item) {
+                let target_slot = can_equip.slot;
+
+                // Whatever already sits in that slot goes back in the backpack.
+                let mut to_unequip: Vec<Entity> = Vec::new();
+                for (item_entity, already_equipped) in (&entities, &equipped).join() {
+                    if already_equipped.owner == entity && already_equipped.slot == target_slot {
+                        to_unequip.push(item_entity);
+                    }
+                }
+
+                for item in to_unequip.iter() {
+                    equipped.remove(*item);
+                    backpack
+                        .insert(*item, InInventory { owner: entity })
+                        .expect("Unable to insert backpack item.");
+
+                    if entity == *player_entity {
+                        gamelog.log(LogEntry::new(LogCategory::Item).text(format!(
+                            "You unequip the {}.",
+                            names.get(*item).unwrap().name
+                        )));
+                    }
+                }
+
+                equipped
+                    .insert(
+                        use_item.item,
+                        Equipped {
+                            owner: entity,
+                            slot: target_slot,
+                        },
+                    )
+                    .expect("Unable to equip item.");
+                backpack.remove(use_item.item);
+
+                if entity == *player_entity {
+                    gamelog.log(LogEntry::new(LogCategory::Item).text(format!(
+                        "You equip the {}.",
+                        names.get(use_item.item).unwrap().name
+                    )));
+                }
+            }
+        }
+
+        wants_use.clear();
+    }
+}
+
+pub struct ItemDropSystem {}
+
+impl<'a> System<'a> for ItemDropSystem {
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToDropItem>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, InInventory>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            entities,
+            mut wants_drop,
+            names,
+            mut positions,
+            mut backpack,
+        ) = data;
+
+        for (entity, to_drop) in (&entities, &wants_drop).join() {
+            let dropper_pos = match positions.get(entity) {
+                Some(pos) => Position { x: pos.x, y: pos.y },
+                None => continue,
+            };
+
+            positions
+                .insert(to_drop.item, dropper_pos)
+                .expect("Unable to insert position");
+            backpack.remove(to_drop.item);
+
+            if entity == *player_entity {
+                gamelog.log(LogEntry::new(LogCategory::Item).text(format!(
+                    "You drop the {}.",
+                    names.get(to_drop.item).unwrap().name
+                )));
+            }
+        }
+
+        wants_drop.clear();
+    }
+}
+
+pub struct ItemDestroySystem {}
+
+impl<'a> System<'a> for ItemDestroySystem {
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToDestroyItem>,
+        ReadStorage<'a, Name>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_entity, mut gamelog, entities, mut wants_destroy, names) = data;
+
+        for (entity, to_destroy) in (&entities, &wants_destroy).join() {
+            if entity == *player_entity {
+                gamelog.log(LogEntry::new(LogCategory::Item).text(format!(
+                    "You destroy the {}.",
+                    names.get(to_destroy.item).unwrap().name
+                )));
+            }
+
+            entities.delete(to_destroy.item).expect("Delete failed");
+        }
+
+        wants_destroy.clear();
+    }
+}
@@ -0,0 +1,742 @@
+use rltk::{BEvent, GameState, Point, RandomNumberGenerator, Rltk, VirtualKeyCode, INPUT};
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use specs::saveload::{SimpleMarker, SimpleMarkerAllocator};
+
+mod inventory_system;
+pub use inventory_system::*;
+
+mod spawner;
+pub use spawner::*;
+
+mod gamelog;
+pub use gamelog::*;
+
+mod gui;
+pub use gui::*;
+
+mod damage_system;
+
+pub use damage_system::*;
+
+mod melee_combat_system;
+pub use melee_combat_system::*;
+
+mod map_indexing_system;
+pub use map_indexing_system::*;
+
+mod monster_ai_system;
+pub use monster_ai_system::*;
+
+mod player;
+pub use player::*;
+
+mod map;
+pub use map::*;
+
+mod map_builders;
+
+mod components;
+pub use components::*;
+
+mod rect;
+pub use rect::*;
+
+mod camera;
+pub use camera::*;
+
+mod visibility_system;
+pub use visibility_system::*;
+
+mod status_effect_system;
+pub use status_effect_system::*;
+
+mod initiative_system;
+pub use initiative_system::*;
+
+mod hunger_system;
+pub use hunger_system::*;
+
+mod door_system;
+pub use door_system::*;
+
+mod trigger_system;
+pub use trigger_system::*;
+
+mod travel;
+pub use travel::*;
+
+mod saveload_system;
+
+mod simulation;
+pub use simulation::*;
+
+mod replay;
+pub use replay::*;
+
+mod raws;
+pub use raws::*;
+
+mod spawn_table;
+pub use spawn_table::*;
+
+#[derive(Default)]
+pub struct GodMode(bool);
+
+/// Seed the run's RandomNumberGenerator was created with. The same seed and
+/// the same inputs always replay the same game.
+#[derive(Default, Clone, Copy)]
+pub struct RunSeed(pub u64);
+
+/// Tally of the current run, shown on the game over screen.
+#[derive(Default)]
+pub struct RunStats {
+    pub kills: i32,
+    pub last_hurt_by: String,
+    pub turns: i32,
+}
+
+/// Shown over the map until the player has taken a few more turns.
+#[derive(Default)]
+pub struct LevelUpBanner {
+    pub level: i32,
+    pub until_turn: i32,
+}
+
+/// How many recorded actions fast forward plays each frame.
+const REPLAY_ACTIONS_PER_FRAME: usize = 10;
+
+/// Size of the levels the dungeon generates.
+pub const MAP_WIDTH: i32 = 80;
+pub const MAP_HEIGHT: i32 = 43;
+
+/// Saved along with the world, so a loaded game picks up where it was left.
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub enum RunState {
+    AwaitingInput,
+    PreRun,
+    PlayerTurn,
+    /// Time passes and everyone but the player takes their turns,
+    /// until it is the player's turn again.
+    Ticking,
+    NextLevel,
+    ShowInventory,
+    ShowDropItem,
+    ShowDestroyItem,
+    /// Never saved, as the item isn't a marked entity: saves made while
+    /// targeting go back to the inventory.
+    #[serde(skip)]
+    ShowTargeting {
+        range: i32,
+        item: Entity,
+        cursor: Point,
+    },
+    GameOver,
+    MainMenu {
+        selection: MainMenuSelection,
+    },
+    Options,
+    SaveGame,
+    ShowLog {
+        offset: usize,
+    },
+    ShowSpawnTables {
+        depth: i32,
+    },
+}
+
+pub struct State {
+    ecs: World,
+    scanlines: bool,
+    recorder: Option<ReplayRecorder>,
+    replay: Option<ReplayPlayback>,
+    travel: Option<Travel>,
+}
+
+impl State {
+    /// A fresh run from `seed`, waiting on the main menu.
+    pub fn new(seed: u64) -> State {
+        let mut state = State {
+            ecs: World::new(),
+            scanlines: true,
+            recorder: None,
+            replay: None,
+            travel: None,
+        };
+        state.new_game(seed);
+        state
+    }
+
+    /// Plays back a recorded run, skipping the main menu.
+    pub fn from_replay(replay: Replay) -> State {
+        let mut state = State::new(replay.seed);
+        state.replay = Some(ReplayPlayback::new(replay));
+        state.ecs.insert(RunState::PreRun);
+        state
+    }
+
+    fn run_systems(&mut self) {
+        let mut initiative = InitiativeSystem {};
+        initiative.run_now(&self.ecs);
+
+        // Indexed before sight too, so closed doors block it on a fresh level.
+        let mut mapindex = MapIndexingSystem {};
+        mapindex.run_now(&self.ecs);
+
+        let mut vis = VisibilitySystem {};
+        vis.run_now(&self.ecs);
+
+        let mut mob = MonsterAI {};
+        mob.run_now(&self.ecs);
+
+        let mut doors = DoorSystem {};
+        doors.run_now(&self.ecs);
+
+        mapindex.run_now(&self.ecs);
+
+        let mut triggers = TriggerSystem {};
+        triggers.run_now(&self.ecs);
+
+        let mut melee = MeleeCombatSystem {};
+        melee.run_now(&self.ecs);
+
+        let mut status_effects = StatusEffectSystem {};
+        status_effects.run_now(&self.ecs);
+
+        let mut hunger = HungerSystem {};
+        hunger.run_now(&self.ecs);
+
+        let mut damage = DamageSystem {};
+        damage.run_now(&self.ecs);
+
+        let mut pickup = ItemCollectionSystem {};
+        pickup.run_now(&self.ecs);
+
+        let mut use_items = ItemUseSystem {};
+        use_items.run_now(&self.ecs);
+
+        let mut drop_items = ItemDropSystem {};
+        drop_items.run_now(&self.ecs);
+
+        let mut destroy_items = ItemDestroySystem {};
+        destroy_items.run_now(&self.ecs);
+
+        self.ecs.maintain();
+    }
+
+    /// Throws away the current world and sets up a fresh run from `seed`.
+    fn new_game(&mut self, seed: u64) {
+        self.ecs = World::new();
+        self.travel = None;
+
+        self.ecs.register::<Item>();
+        self.ecs.register::<Potion>();
+        self.ecs.register::<SufferDamage>();
+        self.ecs.register::<WantsToMelee>();
+        self.ecs.register::<CombatStats>();
+        self.ecs.register::<Position>();
+        self.ecs.register::<Player>();
+        self.ecs.register::<Renderable>();
+        self.ecs.register::<Viewshed>();
+        self.ecs.register::<Monster>();
+        self.ecs.register::<Name>();
+        self.ecs.register::<BlocksTile>();
+        self.ecs.register::<WantsToPickupItem>();
+        self.ecs.register::<InInventory>();
+        self.ecs.register::<WantsToUseItem>();
+        self.ecs.register::<WantsToDropItem>();
+        self.ecs.register::<WantsToDestroyItem>();
+        self.ecs.register::<Equippable>();
+        self.ecs.register::<Equipped>();
+        self.ecs.register::<MeleePowerBonus>();
+        self.ecs.register::<DefenseBonus>();
+        self.ecs.register::<Consumable>();
+        self.ecs.register::<Ranged>();
+        self.ecs.register::<InflictsDamage>();
+        self.ecs.register::<AreaOfEffect>();
+        self.ecs.register::<Confusion>();
+        self.ecs.register::<Poisoned>();
+        self.ecs.register::<Asleep>();
+        self.ecs.register::<LastPathUpdate>();
+        self.ecs.register::<KillingBlow>();
+        self.ecs.register::<Experience>();
+        self.ecs.register::<Initiative>();
+        self.ecs.register::<MyTurn>();
+        self.ecs.register::<ProvidesFood>();
+        self.ecs.register::<HungerClock>();
+        self.ecs.register::<BlocksVisibility>();
+        self.ecs.register::<Door>();
+        self.ecs.register::<Key>();
+        self.ecs.register::<WantsToOpenDoor>();
+        self.ecs.register::<Hidden>();
+        self.ecs.register::<EntryTrigger>();
+        self.ecs.register::<SingleActivation>();
+        self.ecs.register::<Teleports>();
+        self.ecs.register::<EntityMoved>();
+        self.ecs.register::<SimpleMarker<SerializeMe>>();
+        self.ecs.register::<SerializationHelper>();
+
+        self.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+
+        self.ecs.insert(RunSeed(seed));
+        self.ecs.insert(RandomNumberGenerator::seeded(seed));
+        self.ecs.insert(RunStats::default());
+        self.ecs.insert(LevelUpBanner::default());
+
+        let player_entity = spawner::player(&mut self.ecs, 0, 0);
+        self.ecs.insert(player_entity);
+        self.generate_world_map(1);
+
+        self.ecs.insert(GodMode(false));
+        self.ecs.insert(RunState::MainMenu {
+            selection: MainMenuSelection::NewGame,
+        });
+        let mut gamelog = GameLog::default();
+        gamelog.system("Hello, sailor!");
+        gamelog.system(format!("Seed: {}", seed));
+        self.ecs.insert(gamelog);
+    }
+
+    /// Builds a fresh level at `new_depth`, spawns its inhabitants
+    /// and moves the player to its starting position.
+    fn generate_world_map(&mut self, new_depth: i32) {
+        let mut builder = {
+            let mut rng = self.ecs.write_resource::<RandomNumberGenerator>();
+            let mut builder =
+                map_builders::random_builder(new_depth, MAP_WIDTH, MAP_HEIGHT, &mut rng);
+            builder.build_map(&mut rng);
+            builder
+        };
+        builder.spawn_entities(&mut self.ecs);
+        self.ecs.insert(builder.get_map());
+
+        let start = builder.get_starting_position();
+        self.ecs.insert(Point::new(start.x, start.y));
+
+        let player_entity = *self.ecs.fetch::<Entity>();
+        let mut positions = self.ecs.write_storage::<Position>();
+        if let Some(player_pos) = positions.get_mut(player_entity) {
+            *player_pos = start;
+        }
+
+        let mut viewsheds = self.ecs.write_storage::<Viewshed>();
+        if let Some(viewshed) = viewsheds.get_mut(player_entity) {
+            viewshed.dirty = true;
+        }
+    }
+
+    /// Everything but the player and what they carry stays behind
+    /// when changing level.
+    fn entities_to_remove_on_level_change(&mut self) -> Vec<Entity> {
+        let entities = self.ecs.entities();
+        let player_entity = self.ecs.fetch::<Entity>();
+        let backpack = self.ecs.read_storage::<InInventory>();
+        let equipped = self.ecs.read_storage::<Equipped>();
+
+        let mut to_delete: Vec<Entity> = Vec::new();
+        for entity in entities.join() {
+            if entity == *player_entity {
+                continue;
+            }
+
+            if let Some(item) = backpack.get(entity) {
+                if item.owner == *player_entity {
+                    continue;
+                }
+            }
+
+            if let Some(item) = equipped.get(entity) {
+                if item.owner == *player_entity {
+                    continue;
+                }
+            }
+
+            to_delete.push(entity);
+        }
+
+        to_delete
+    }
+
+    /// Moves the run-state machine along for the states that don't wait on
+    /// the player. Menus and `AwaitingInput` are returned as they are.
+    fn advance(&mut self, runstate: RunState) -> RunState {
+        match runstate {
+            RunState::PreRun => {
+                self.run_systems();
+                RunState::Ticking
+            }
+            RunState::PlayerTurn => {
+                self.run_systems();
+                self.ecs.write_resource::<RunStats>().turns += 1;
+                let player_entity = *self.ecs.fetch::<Entity>();
+                self.ecs.write_storage::<MyTurn>().remove(player_entity);
+                RunState::Ticking
+            }
+            RunState::Ticking => loop {
+                if self.is_player_turn() {
+                    return RunState::AwaitingInput;
+                }
+                self.run_systems();
+                damage_system::delete_the_dead(&mut self.ecs);
+                if *self.ecs.fetch::<RunState>() == RunState::GameOver {
+                    return RunState::GameOver;
+                }
+            },
+            RunState::NextLevel => {
+                self.goto_next_level();
+                RunState::PreRun
+            }
+            _ => runstate,
+        }
+    }
+
+    /// Runs the states that don't need the player, the same way `tick`
+    /// would frame after frame, until input is needed or the game is over.
+    fn run_until_input(&mut self) {
+        loop {
+            let mut runstate = *self.ecs.fetch::<RunState>();
+            if runstate == RunState::AwaitingInput {
+                if !skip_turn_if_asleep(&self.ecs) {
+                    return;
+                }
+                runstate = RunState::PlayerTurn;
+            }
+
+            let newrunstate = self.advance(runstate);
+            if newrunstate == runstate {
+                return;
+            }
+            self.set_run_state(newrunstate);
+        }
+    }
+
+    /// Input is only taken when the initiative system has given the player a turn.
+    fn is_player_turn(&self) -> bool {
+        let player_entity = *self.ecs.fetch::<Entity>();
+        self.ecs
+            .read_storage::<MyTurn>()
+            .get(player_entity)
+            .is_some()
+    }
+
+    fn set_run_state(&mut self, runstate: RunState) {
+        {
+            let mut runwriter = self.ecs.write_resource::<RunState>();
+            *runwriter = runstate;
+        }
+        damage_system::delete_the_dead(&mut self.ecs);
+    }
+
+    /// Takes the player's turn from the replay instead of the keyboard.
+    /// SPACE plays one action, F toggles fast forward and ESCAPE hands
+    /// control back to the player.
+    fn replay_input(&mut self, ctx: &mut Rltk) -> RunState {
+        if !self.is_player_turn() {
+            return RunState::Ticking;
+        }
+
+        if skip_turn_if_asleep(&self.ecs) {
+            return RunState::PlayerTurn;
+        }
+
+        let replay = self.replay.as_mut().expect("No replay is playing");
+        let mut steps = 0;
+        match ctx.key {
+            Some(VirtualKeyCode::Escape) => {
+                self.stop_replay("You take over from the replay.");
+                return RunState::AwaitingInput;
+            }
+            Some(VirtualKeyCode::F) => replay.fast_forward = !replay.fast_forward,
+            Some(VirtualKeyCode::Space) => steps = 1,
+            _ => {}
+        }
+        if replay.fast_forward {
+            steps = REPLAY_ACTIONS_PER_FRAME;
+        }
+
+        let mut newrunstate = RunState::AwaitingInput;
+        for step in 0..steps {
+            if step > 0 {
+                self.set_run_state(newrunstate);
+                self.run_until_input();
+                newrunstate = *self.ecs.fetch::<RunState>();
+                if newrunstate != RunState::AwaitingInput {
+                    break;
+                }
+            }
+
+            let recorded = self.replay.as_mut().and_then(|r| r.actions.pop_front());
+            let recorded = match recorded {
+                Some(recorded) => recorded,
+                None => {
+                    self.stop_replay("End of the replay, you have control.");
+                    break;
+                }
+            };
+
+            let turn = self.ecs.fetch::<RunStats>().turns;
+            if recorded.turn != turn {
+                let mut gamelog = self.ecs.fetch_mut::<GameLog>();
+                gamelog.system(format!(
+                    "Replay out of sync: recorded turn {}, playing turn {}.",
+                    recorded.turn, turn
+                ));
+            }
+            newrunstate = perform_action(self, recorded.action);
+        }
+
+        newrunstate
+    }
+
+    fn stop_replay(&mut self, message: &str) {
+        self.replay = None;
+        let mut gamelog = self.ecs.fetch_mut::<GameLog>();
+        gamelog.system(message);
+    }
+
+    /// Saves the run in progress, if any, before the window closes. A
+    /// replay is never saved over the player's own run.
+    fn save_and_quit(&mut self, ctx: &mut Rltk) {
+        let runstate = *self.ecs.fetch::<RunState>();
+        let in_run = !matches!(
+            runstate,
+            RunState::MainMenu { .. } | RunState::Options | RunState::GameOver
+        );
+        if in_run && self.replay.is_none() {
+            saveload_system::save_game(&mut self.ecs, runstate);
+        }
+        ctx.quit();
+    }
+
+    fn goto_next_level(&mut self) {
+        let to_delete = self.entities_to_remove_on_level_change();
+        for target in to_delete {
+            self.ecs
+                .delete_entity(target)
+                .expect("Unable to delete entity on level change");
+        }
+
+        let current_depth = self.ecs.fetch::<Map>().depth;
+        self.generate_world_map(current_depth + 1);
+
+        let mut gamelog = self.ecs.fetch_mut::<GameLog>();
+        gamelog.system("You descend to the next level.");
+    }
+}
+
+impl GameState for State {
+    fn tick(&mut self, ctx: &mut Rltk) {
+        let mut close_requested = false;
+        while let Some(event) = INPUT.lock().pop() {
+            if event == BEvent::CloseRequested {
+                close_requested = true;
+            }
+        }
+        if close_requested {
+            self.save_and_quit(ctx);
+            return;
+        }
+
+        ctx.cls();
+
+        let mut newrunstate;
+        {
+            let runstate = self.ecs.fetch::<RunState>();
+            newrunstate = *runstate;
+        }
+        let in_menu = matches!(
+            newrunstate,
+            RunState::GameOver
+                | RunState::MainMenu { .. }
+                | RunState::Options
+                | RunState::ShowLog { .. }
+                | RunState::ShowSpawnTables { .. }
+        );
+
+        if !in_menu {
+            camera::render_camera(&self.ecs, ctx);
+
+            let godmode: specs::shred::Fetch<GodMode> = self.ecs.fetch();
+            ctx.print(1, 1, format!("God: {}", godmode.0));
+            ctx.print(1, 2, format!("FPS: {}", ctx.fps));
+            if let Some(replay) = &self.replay {
+                let speed = if replay.fast_forward {
+                    "fast forward"
+                } else {
+                    "step"
+                };
+                ctx.print(
+                    1,
+                    3,
+                    format!(
+                        "Replay ({}): SPACE step, F fast forward, ESCAPE take over",
+                        speed
+                    ),
+                );
+            }
+
+            draw_ui(&self.ecs, ctx);
+        }
+
+        match newrunstate {
+            RunState::PreRun | RunState::PlayerTurn | RunState::Ticking | RunState::NextLevel => {
+                newrunstate = self.advance(newrunstate);
+            }
+            RunState::AwaitingInput => {
+                newrunstate = if self.replay.is_some() {
+                    self.replay_input(ctx)
+                } else {
+                    player_input(self, ctx)
+                };
+            }
+            RunState::ShowInventory => {
+                let (result, item) = gui::show_inventory(&self.ecs, ctx);
+                match result {
+                    ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    ItemMenuResult::NoResponse => {}
+                    ItemMenuResult::Selected => {
+                        let item = item.unwrap();
+                        let range = self.ecs.read_storage::<Ranged>().get(item).map(|r| r.range);
+                        if let Some(range) = range {
+                            newrunstate = RunState::ShowTargeting {
+                                range,
+                                item,
+                                cursor: *self.ecs.fetch::<Point>(),
+                            };
+                        } else {
+                            let slot = backpack_slot(&self.ecs, item)
+                                .expect("Selected item is not in the backpack");
+                            newrunstate =
+                                perform_action(self, PlayerAction::UseItem { slot, target: None });
+                        }
+                    }
+                }
+            }
+            RunState::GameOver => match gui::game_over(&self.ecs, ctx) {
+                GameOverResult::NoSelection => {}
+                GameOverResult::QuitToMenu => {
+                    // The world is rebuilt right away, so the menu's New Game
+                    // starts from a clean slate.
+                    self.recorder = None;
+                    self.replay = None;
+                    self.new_game(RandomNumberGenerator::new().next_u64());
+                    return;
+                }
+            },
+            RunState::MainMenu { selection } => {
+                match gui::main_menu(ctx, selection, saveload_system::does_save_exist()) {
+                    MainMenuResult::NoSelection { selected } => {
+                        newrunstate = RunState::MainMenu {
+                            selection: selected,
+                        }
+                    }
+                    MainMenuResult::Selected { selected } => match selected {
+                        MainMenuSelection::NewGame => {
+                            let seed = self.ecs.fetch::<RunSeed>().0;
+                            println!("Seed: {}", seed);
+                            self.recorder = Some(ReplayRecorder::create(REPLAY_FILE, seed));
+                            newrunstate = RunState::PreRun;
+                        }
+                        MainMenuSelection::LoadGame => {
+                            // A loaded game doesn't start from its seed, so it can't be replayed.
+                            self.recorder = None;
+                            newrunstate = saveload_system::load_game(&mut self.ecs);
+                            // Permadeath: loading uses the save up, and it is only
+                            // written again when the run is put away.
+                            saveload_system::delete_save();
+                            // Rebuilds what isn't saved, like the map's content index. Nobody
+                            // acts, as turns are only taken while ticking.
+                            self.run_systems();
+                        }
+                        MainMenuSelection::Options => newrunstate = RunState::Options,
+                        MainMenuSelection::Quit => self.save_and_quit(ctx),
+                    },
+                }
+            }
+            RunState::SaveGame => {
+                saveload_system::save_game(&mut self.ecs, RunState::AwaitingInput);
+                self.recorder = None;
+                // Same as after a game over: the menu always sits on a fresh world.
+                self.new_game(RandomNumberGenerator::new().next_u64());
+                self.ecs.insert(RunState::MainMenu {
+                    selection: MainMenuSelection::LoadGame,
+                });
+                return;
+            }
+            RunState::Options => match gui::options_menu(ctx, self.scanlines) {
+                OptionsMenuResult::NoSelection => {}
+                OptionsMenuResult::ToggleScanlines => {
+                    self.scanlines = !self.scanlines;
+                    ctx.with_post_scanlines(self.scanlines);
+                }
+                OptionsMenuResult::Back => {
+                    newrunstate = RunState::MainMenu {
+                        selection: MainMenuSelection::Options,
+                    }
+                }
+            },
+            RunState::ShowTargeting {
+                range,
+                item,
+                mut cursor,
+            } => {
+                let (result, target) = gui::ranged_target(&self.ecs, ctx, range, &mut cursor);
+                match result {
+                    ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    ItemMenuResult::NoResponse => {
+                        newrunstate = RunState::ShowTargeting {
+                            range,
+                            item,
+                            cursor,
+                        }
+                    }
+                    ItemMenuResult::Selected => {
+                        let slot = backpack_slot(&self.ecs, item)
+                            .expect("Targeted item is not in the backpack");
+                        newrunstate = perform_action(self, PlayerAction::UseItem { slot, target });
+                    }
+                }
+            }
+            RunState::ShowLog { offset } => match gui::show_log(&self.ecs, ctx, offset) {
+                LogViewResult::Close => newrunstate = RunState::AwaitingInput,
+                LogViewResult::Scroll { offset } => newrunstate = RunState::ShowLog { offset },
+            },
+            RunState::ShowSpawnTables { depth } => match gui::show_spawn_tables(ctx, depth) {
+                SpawnTableViewResult::Close => newrunstate = RunState::AwaitingInput,
+                SpawnTableViewResult::Depth { depth } => {
+                    newrunstate = RunState::ShowSpawnTables { depth }
+                }
+            },
+            RunState::ShowDropItem => {
+                let (result, item) = gui::drop_item_menu(&self.ecs, ctx);
+                match result {
+                    ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    ItemMenuResult::NoResponse => {}
+                    ItemMenuResult::Selected => {
+                        let slot = backpack_slot(&self.ecs, item.unwrap())
+                            .expect("Selected item is not in the backpack");
+                        newrunstate = perform_action(self, PlayerAction::DropItem { slot });
+                    }
+                }
+            }
+            RunState::ShowDestroyItem => {
+                let (result, item) = gui::destroy_item_menu(&self.ecs, ctx);
+                match result {
+                    ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    ItemMenuResult::NoResponse => {}
+                    ItemMenuResult::Selected => {
+                        let slot = backpack_slot(&self.ecs, item.unwrap())
+                            .expect("Selected item is not in the backpack");
+                        newrunstate = perform_action(self, PlayerAction::DestroyItem { slot });
+                    }
+                }
+            }
+        }
+
+        {
+            let mut runwriter = self.ecs.write_resource::<RunState>();
+            *runwriter = newrunstate;
+        }
+        damage_system::delete_the_dead(&mut self.ecs);
+    }
+}
@@ -1,17 +1,16 @@
-use std::cmp::{max, min};
+use crate::Rect;
+use rltk::{Algorithm2D, BaseMap, Point};
+use serde::{Deserialize, Serialize};
+use specs::Entity;
 
-use crate::{GodMode, Player, Rect, Viewshed};
-use rltk::{Algorithm2D, BaseMap, Point, RandomNumberGenerator, Rltk, RGB};
-use specs::{Entity, World, WorldExt};
-
-use super::{MAP_HEIGHT, MAP_WIDTH};
-
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
 pub enum TileType {
     Wall,
     Floor,
+    DownStairs,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Map {
     pub tiles: Vec<TileType>,
     pub rooms: Vec<Rect>,
@@ -20,12 +19,21 @@ pub struct Map {
     pub revealed_tiles: Vec<bool>,
     pub visible_tiles: Vec<bool>,
     pub blocked: Vec<bool>,
+    // Rebuilt by the MapIndexingSystem, so it is not saved.
+    #[serde(skip_serializing, skip_deserializing)]
     pub tile_content: Vec<Vec<Entity>>,
+    // Tiles something stands in the way of sight, like a closed door.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub view_blocked: Vec<bool>,
+    // Closed doors that anyone can open, which paths may go through.
+    #[serde(skip_serializing, skip_deserializing)]
+    pub closed_doors: Vec<bool>,
+    pub depth: i32,
 }
 
 impl BaseMap for Map {
     fn is_opaque(&self, idx: usize) -> bool {
-        self.tiles[idx] == TileType::Wall
+        self.tiles[idx] == TileType::Wall || self.view_blocked[idx]
     }
 
     fn get_available_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
@@ -79,44 +87,59 @@ impl Algorithm2D for Map {
     }
 }
 
-impl Map {
-    pub fn map_index(&self, x: i32, y: i32) -> usize {
-        (y as usize * 80) + x as usize
+/// The map as someone who knows where some traps are paths over it: those
+/// tiles are left out, unless one is where they are headed.
+pub struct TrapAwareMap<'a> {
+    pub map: &'a Map,
+    pub known_traps: &'a [usize],
+    pub goal: Option<usize>,
+}
+
+impl BaseMap for TrapAwareMap<'_> {
+    fn is_opaque(&self, idx: usize) -> bool {
+        self.map.is_opaque(idx)
     }
 
-    fn apply_room_to_map(&mut self, room: &Rect) {
-        for y in room.y1 + 1..=room.y2 {
-            for x in room.x1 + 1..=room.x2 {
-                let index = self.map_index(x, y);
-                self.tiles[index] = TileType::Floor;
-            }
-        }
+    fn get_available_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
+        let mut exits = self.map.get_available_exits(idx);
+        exits.retain(|(exit, _)| Some(*exit) == self.goal || !self.known_traps.contains(exit));
+        exits
     }
 
-    fn apply_horizontal_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
-        for x in min(x1, x2)..=max(x1, x2) {
-            let index = self.map_index(x, y);
-            if index > 0 && index < MAP_WIDTH as usize * MAP_HEIGHT as usize {
-                self.tiles[index] = TileType::Floor;
-            }
-        }
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        self.map.get_pathing_distance(idx1, idx2)
     }
+}
 
-    fn apply_vertical_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
-        for y in min(y1, y2)..=max(y1, y2) {
-            let index = self.map_index(x, y);
-            if index > 0 && index < MAP_WIDTH as usize * MAP_HEIGHT as usize {
-                self.tiles[index] = TileType::Floor;
-            }
+impl Map {
+    /// A map of the given size made only of walls, ready to be carved by a `MapBuilder`.
+    pub fn new(width: i32, height: i32, depth: i32) -> Map {
+        let count = (width * height) as usize;
+        Map {
+            tiles: vec![TileType::Wall; count],
+            revealed_tiles: vec![false; count],
+            visible_tiles: vec![false; count],
+            blocked: vec![false; count],
+            tile_content: vec![Vec::new(); count],
+            view_blocked: vec![false; count],
+            closed_doors: vec![false; count],
+            rooms: Vec::new(),
+            width,
+            height,
+            depth,
         }
     }
 
+    pub fn map_index(&self, x: i32, y: i32) -> usize {
+        (y * self.width + x) as usize
+    }
+
     fn is_exit_valid(&self, x: i32, y: i32) -> bool {
         if x < 1 || x > (self.width - 1) || y < 1 || y > (self.height - 1) {
             return false;
         }
         let idx = self.map_index(x, y);
-        !self.blocked[idx]
+        !self.blocked[idx] || self.closed_doors[idx]
     }
 
     pub fn populate_blocked(&mut self) {
@@ -129,107 +152,7 @@ impl Map {
         for content in self.tile_content.iter_mut() {
             content.clear();
         }
-    }
-
-    pub fn new_map_rooms_and_corridors() -> Self {
-        // TODO: Use RNG resource here
-        let mut map = Map {
-            tiles: vec![TileType::Wall; MAP_WIDTH as usize * MAP_HEIGHT as usize],
-            revealed_tiles: vec![false; MAP_WIDTH as usize * MAP_HEIGHT as usize],
-            visible_tiles: vec![false; MAP_WIDTH as usize * MAP_HEIGHT as usize],
-            blocked: vec![false; MAP_WIDTH as usize * MAP_HEIGHT as usize],
-            tile_content: vec![Vec::new(); MAP_WIDTH as usize * MAP_HEIGHT as usize],
-            rooms: Vec::new(),
-            width: MAP_WIDTH,
-            height: MAP_HEIGHT,
-        };
-
-        let max_rooms = 30;
-        let min_size: i32 = 6;
-        let max_size: i32 = 10;
-
-        let mut rng = RandomNumberGenerator::new();
-
-        for _ in 0..max_rooms {
-            let w = rng.range(min_size, max_size);
-            let h = rng.range(min_size, max_size);
-            let x = rng.roll_dice(1, MAP_WIDTH - w - 1) - 1;
-            let y = rng.roll_dice(1, MAP_HEIGHT - h - 1) - 1;
-
-            let new_room = Rect::new(x, y, w, h);
-            let mut ok = true;
-            for other_room in map.rooms.iter() {
-                if new_room.intersect(other_room) {
-                    ok = false
-                }
-            }
-
-            if ok {
-                map.apply_room_to_map(&new_room);
-
-                // 50% chance to make a vertical tunnel first and then a
-                // horizontal tunnel or vice-versa. This is because
-                // if you have two squares that are distant from each other
-                // you have two ways of connecting them (both ways form an L shape).
-                // The randomness makes it less repetitive.
-                if !map.rooms.is_empty() {
-                    let (new_x, new_y) = new_room.center();
-                    let (prev_x, prev_y) = map.rooms[map.rooms.len() - 1].center();
-                    map.apply_horizontal_tunnel(prev_x, new_x, prev_y);
-                    map.apply_vertical_tunnel(prev_y, new_y, new_x);
-                    if rng.range(0, 2) == 1 {
-                        map.apply_horizontal_tunnel(prev_x, new_x, prev_y);
-                        map.apply_vertical_tunnel(prev_y, new_y, new_x);
-                    } else {
-                        map.apply_vertical_tunnel(prev_y, new_y, prev_x);
-                        map.apply_horizontal_tunnel(prev_x, new_x, new_y);
-                    }
-                }
-
-                map.rooms.push(new_room);
-            }
-        }
-
-        map
-    }
-
-    pub fn draw_map(ecs: &World, ctx: &mut Rltk) {
-        let _players = ecs.write_storage::<Player>();
-        let _viewsheds = ecs.write_storage::<Viewshed>();
-        let map = ecs.fetch::<Map>();
-        let godmode = ecs.fetch::<GodMode>();
-
-        let mut x = 0;
-        let mut y = 0;
-
-        // Use enumerate to get the index of each tile,
-        // the tile index allows us to find if that tile
-        // is True in map.revealed_tiles (since map.revealed_tiles[0]
-        // maps to map.tiles[0])
-        for (index, tile) in map.tiles.iter().enumerate() {
-            if map.revealed_tiles[index] || godmode.0 {
-                let glyph;
-                let mut fg;
-                match tile {
-                    TileType::Floor => {
-                        fg = RGB::from_f32(0.5, 0.5, 0.5);
-                        glyph = rltk::to_cp437('.');
-                    }
-                    TileType::Wall => {
-                        glyph = rltk::to_cp437('#');
-                        fg = RGB::from_f32(0., 1.0, 0.);
-                    }
-                }
-                if !map.visible_tiles[index] {
-                    fg = fg.to_greyscale()
-                }
-                ctx.set(x, y, fg, RGB::from_f32(0., 0., 0.), glyph);
-            }
-            x += 1;
-            if x > MAP_WIDTH - 1 {
-                x = 0;
-                y += 1;
-            }
-        }
+        self.view_blocked.fill(false);
+        self.closed_doors.fill(false);
     }
 }
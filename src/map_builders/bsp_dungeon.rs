@@ -0,0 +1,178 @@
+use super::{apply_room_to_map, fallback_room, MapBuilder};
+use crate::{spawner, Map, Position, Rect, TileType};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+// Number of attempts at carving a room out of a random partition.
+const MAX_ATTEMPTS: i32 = 240;
+
+pub struct BspDungeonBuilder {
+    map: Map,
+    starting_position: Position,
+    rects: Vec<Rect>,
+}
+
+impl MapBuilder for BspDungeonBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        for room in self.map.rooms.iter().skip(1) {
+            spawner::spawn_room(ecs, &self.map, room, self.map.depth);
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position
+    }
+}
+
+impl BspDungeonBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> BspDungeonBuilder {
+        BspDungeonBuilder {
+            map: Map::new(width, height, new_depth),
+            starting_position: Position { x: 0, y: 0 },
+            rects: Vec::new(),
+        }
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        let mut rooms: Vec<Rect> = Vec::new();
+
+        self.rects.clear();
+        self.rects.push(Rect::new(
+            2,
+            2,
+            i32::max(self.map.width - 5, 1),
+            i32::max(self.map.height - 5, 1),
+        ));
+        let first_room = self.rects[0];
+        self.add_subrects(first_room);
+
+        // Keep splitting random partitions in four, and try to fit
+        // a room inside of each one we pick.
+        for _ in 0..MAX_ATTEMPTS {
+            let rect = self.rects[rng.range(0, self.rects.len() as i32) as usize];
+            let candidate = random_sub_rect(rect, rng);
+
+            if self.is_possible(candidate) {
+                apply_room_to_map(&mut self.map, &candidate);
+                rooms.push(candidate);
+                self.add_subrects(rect);
+            }
+        }
+
+        // Small maps may have no room for a partition's room at all.
+        if rooms.is_empty() {
+            let room = fallback_room(&self.map);
+            apply_room_to_map(&mut self.map, &room);
+            rooms.push(room);
+        }
+
+        // Connecting rooms from left to right keeps the corridors short.
+        rooms.sort_by_key(|room| room.x1);
+
+        for pair in rooms.windows(2) {
+            let start_x = pair[0].x1 + 1 + rng.range(0, i32::abs(pair[0].x1 - pair[0].x2));
+            let start_y = pair[0].y1 + 1 + rng.range(0, i32::abs(pair[0].y1 - pair[0].y2));
+            let end_x = pair[1].x1 + 1 + rng.range(0, i32::abs(pair[1].x1 - pair[1].x2));
+            let end_y = pair[1].y1 + 1 + rng.range(0, i32::abs(pair[1].y1 - pair[1].y2));
+            self.draw_corridor(start_x, start_y, end_x, end_y);
+        }
+
+        let (stairs_x, stairs_y) = rooms[rooms.len() - 1].center();
+        let stairs_index = self.map.map_index(stairs_x, stairs_y);
+        self.map.tiles[stairs_index] = TileType::DownStairs;
+
+        let (start_x, start_y) = rooms[0].center();
+        self.starting_position = Position {
+            x: start_x,
+            y: start_y,
+        };
+        self.map.rooms = rooms;
+    }
+
+    fn add_subrects(&mut self, rect: Rect) {
+        let width = i32::abs(rect.x1 - rect.x2);
+        let height = i32::abs(rect.y1 - rect.y2);
+        let half_width = i32::max(width / 2, 1);
+        let half_height = i32::max(height / 2, 1);
+
+        self.rects
+            .push(Rect::new(rect.x1, rect.y1, half_width, half_height));
+        self.rects.push(Rect::new(
+            rect.x1,
+            rect.y1 + half_height,
+            half_width,
+            half_height,
+        ));
+        self.rects.push(Rect::new(
+            rect.x1 + half_width,
+            rect.y1,
+            half_width,
+            half_height,
+        ));
+        self.rects.push(Rect::new(
+            rect.x1 + half_width,
+            rect.y1 + half_height,
+            half_width,
+            half_height,
+        ));
+    }
+
+    // A room fits if it, plus a one tile margin, is inside the map
+    // and only covers walls.
+    fn is_possible(&self, rect: Rect) -> bool {
+        for y in rect.y1 - 1..=rect.y2 + 1 {
+            for x in rect.x1 - 1..=rect.x2 + 1 {
+                if x < 1 || x > self.map.width - 2 || y < 1 || y > self.map.height - 2 {
+                    return false;
+                }
+
+                let index = self.map.map_index(x, y);
+                if self.map.tiles[index] != TileType::Wall {
+                    return false;
+                }
+            }
+        }
+
+        true
+    }
+
+    fn draw_corridor(&mut self, x1: i32, y1: i32, x2: i32, y2: i32) {
+        let mut x = x1;
+        let mut y = y1;
+
+        while x != x2 || y != y2 {
+            if x < x2 {
+                x += 1;
+            } else if x > x2 {
+                x -= 1;
+            } else if y < y2 {
+                y += 1;
+            } else if y > y2 {
+                y -= 1;
+            }
+
+            let index = self.map.map_index(x, y);
+            self.map.tiles[index] = TileType::Floor;
+        }
+    }
+}
+
+fn random_sub_rect(rect: Rect, rng: &mut RandomNumberGenerator) -> Rect {
+    let rect_width = i32::abs(rect.x1 - rect.x2);
+    let rect_height = i32::abs(rect.y1 - rect.y2);
+
+    let w = i32::max(3, rng.roll_dice(1, rect_width.clamp(1, 10)) - 1) + 1;
+    let h = i32::max(3, rng.roll_dice(1, rect_height.clamp(1, 10)) - 1) + 1;
+    let x = rect.x1 + rng.roll_dice(1, 6) - 1;
+    let y = rect.y1 + rng.roll_dice(1, 6) - 1;
+
+    Rect::new(x, y, w, h)
+}
@@ -0,0 +1,107 @@
+use super::{
+    find_central_floor, generate_regions, remove_unreachable_areas_returning_most_distant,
+    MapBuilder,
+};
+use crate::{spawner, Map, Position, TileType};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+const ITERATIONS: i32 = 15;
+
+pub struct CellularAutomataBuilder {
+    map: Map,
+    starting_position: Position,
+    spawn_regions: Vec<Vec<usize>>,
+}
+
+impl MapBuilder for CellularAutomataBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        let start_index = self
+            .map
+            .map_index(self.starting_position.x, self.starting_position.y);
+
+        for area in self.spawn_regions.iter() {
+            if !area.contains(&start_index) {
+                spawner::spawn_region(ecs, &self.map, area, self.map.depth);
+            }
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position
+    }
+}
+
+impl CellularAutomataBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> CellularAutomataBuilder {
+        CellularAutomataBuilder {
+            map: Map::new(width, height, new_depth),
+            starting_position: Position { x: 0, y: 0 },
+            spawn_regions: Vec::new(),
+        }
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        // Start from noise: a bit more than half the map is floor.
+        for y in 1..self.map.height - 1 {
+            for x in 1..self.map.width - 1 {
+                let index = self.map.map_index(x, y);
+                if rng.roll_dice(1, 100) > 55 {
+                    self.map.tiles[index] = TileType::Floor;
+                } else {
+                    self.map.tiles[index] = TileType::Wall;
+                }
+            }
+        }
+
+        // A tile becomes a wall when it's crowded by walls or completely
+        // isolated, otherwise it's floor. Repeating this smooths the noise
+        // into caves.
+        for _ in 0..ITERATIONS {
+            let mut new_tiles = self.map.tiles.clone();
+
+            for y in 1..self.map.height - 1 {
+                for x in 1..self.map.width - 1 {
+                    let mut neighbours = 0;
+                    for dy in -1..=1 {
+                        for dx in -1..=1 {
+                            if (dx != 0 || dy != 0)
+                                && self.map.tiles[self.map.map_index(x + dx, y + dy)]
+                                    == TileType::Wall
+                            {
+                                neighbours += 1;
+                            }
+                        }
+                    }
+
+                    let index = self.map.map_index(x, y);
+                    if neighbours > 4 || neighbours == 0 {
+                        new_tiles[index] = TileType::Wall;
+                    } else {
+                        new_tiles[index] = TileType::Floor;
+                    }
+                }
+            }
+
+            self.map.tiles = new_tiles;
+        }
+
+        self.starting_position = find_central_floor(&self.map);
+        let start_index = self
+            .map
+            .map_index(self.starting_position.x, self.starting_position.y);
+        let exit_index =
+            remove_unreachable_areas_returning_most_distant(&mut self.map, start_index);
+        self.map.tiles[exit_index] = TileType::DownStairs;
+
+        self.spawn_regions = generate_regions(&self.map);
+    }
+}
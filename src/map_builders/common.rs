@@ -0,0 +1,147 @@
+use std::cmp::{max, min};
+use std::collections::BTreeMap;
+
+use crate::{Map, Position, Rect, TileType};
+
+// Size of the square chunks that spawn regions are cut into
+// on maps that don't have rooms.
+const REGION_SIZE: i32 = 12;
+
+pub fn apply_room_to_map(map: &mut Map, room: &Rect) {
+    for y in room.y1 + 1..=room.y2 {
+        for x in room.x1 + 1..=room.x2 {
+            let index = map.map_index(x, y);
+            map.tiles[index] = TileType::Floor;
+        }
+    }
+}
+
+// The whole map inside its outer wall, for when no random room fits.
+pub fn fallback_room(map: &Map) -> Rect {
+    Rect::new(0, 0, max(map.width - 2, 1), max(map.height - 2, 1))
+}
+
+pub fn apply_horizontal_tunnel(map: &mut Map, x1: i32, x2: i32, y: i32) {
+    for x in min(x1, x2)..=max(x1, x2) {
+        let index = map.map_index(x, y);
+        if index > 0 && index < map.tiles.len() {
+            map.tiles[index] = TileType::Floor;
+        }
+    }
+}
+
+pub fn apply_vertical_tunnel(map: &mut Map, y1: i32, y2: i32, x: i32) {
+    for y in min(y1, y2)..=max(y1, y2) {
+        let index = map.map_index(x, y);
+        if index > 0 && index < map.tiles.len() {
+            map.tiles[index] = TileType::Floor;
+        }
+    }
+}
+
+// Corridor tiles in the wall around a room, squeezed between two walls,
+// where a door fits.
+pub fn find_doorways(map: &Map) -> Vec<usize> {
+    let is_wall = |x: i32, y: i32| map.tiles[map.map_index(x, y)] == TileType::Wall;
+
+    let mut doorways: Vec<usize> = Vec::new();
+    for room in map.rooms.iter() {
+        let mut ring: Vec<(i32, i32)> = Vec::new();
+        for x in room.x1..=room.x2 + 1 {
+            ring.push((x, room.y1));
+            ring.push((x, room.y2 + 1));
+        }
+        for y in room.y1 + 1..=room.y2 {
+            ring.push((room.x1, y));
+            ring.push((room.x2 + 1, y));
+        }
+
+        for (x, y) in ring {
+            if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 {
+                continue;
+            }
+            let index = map.map_index(x, y);
+            if map.tiles[index] != TileType::Floor || doorways.contains(&index) {
+                continue;
+            }
+            let walled_sideways = is_wall(x - 1, y) && is_wall(x + 1, y);
+            let walled_lengthways = is_wall(x, y - 1) && is_wall(x, y + 1);
+            if walled_sideways || walled_lengthways {
+                doorways.push(index);
+            }
+        }
+    }
+
+    doorways
+}
+
+// Floor tile closest to the middle of the map.
+pub fn find_central_floor(map: &Map) -> Position {
+    let center_x = map.width / 2;
+    let center_y = map.height / 2;
+
+    let index = map
+        .tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, tile)| **tile == TileType::Floor)
+        .map(|(index, _)| index as i32)
+        .min_by_key(|index| {
+            let dx = index % map.width - center_x;
+            let dy = index / map.width - center_y;
+            dx * dx + dy * dy
+        })
+        .expect("Map has no floor tiles.");
+
+    Position {
+        x: index % map.width,
+        y: index / map.width,
+    }
+}
+
+// Walls off every floor tile that can't be walked to from the start,
+// and returns the index of the reachable tile that is the furthest away.
+pub fn remove_unreachable_areas_returning_most_distant(map: &mut Map, start_index: usize) -> usize {
+    map.populate_blocked();
+    let map_starts: Vec<usize> = vec![start_index];
+    let dijkstra_map = rltk::DijkstraMap::new(
+        map.width as usize,
+        map.height as usize,
+        &map_starts,
+        map,
+        200.0,
+    );
+
+    let mut exit_tile = (start_index, 0.0f32);
+    for (index, tile) in map.tiles.iter_mut().enumerate() {
+        if *tile == TileType::Floor {
+            let distance = dijkstra_map.map[index];
+            if distance == f32::MAX {
+                *tile = TileType::Wall;
+            } else if distance > exit_tile.1 {
+                exit_tile = (index, distance);
+            }
+        }
+    }
+
+    exit_tile.0
+}
+
+// Groups floor tiles into square chunks, used as spawn areas
+// by builders that don't produce rooms.
+pub fn generate_regions(map: &Map) -> Vec<Vec<usize>> {
+    let mut regions: BTreeMap<(i32, i32), Vec<usize>> = BTreeMap::new();
+
+    for (index, tile) in map.tiles.iter().enumerate() {
+        if *tile == TileType::Floor {
+            let x = index as i32 % map.width;
+            let y = index as i32 / map.width;
+            regions
+                .entry((x / REGION_SIZE, y / REGION_SIZE))
+                .or_default()
+                .push(index);
+        }
+    }
+
+    regions.into_values().collect()
+}
@@ -0,0 +1,98 @@
+use super::{generate_regions, remove_unreachable_areas_returning_most_distant, MapBuilder};
+use crate::{spawner, Map, Position, TileType};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+// How many steps a digger takes before it gives up.
+const DRUNKEN_LIFETIME: i32 = 400;
+// Stop digging once this percentage of the map is floor.
+const FLOOR_PERCENT: usize = 50;
+
+pub struct DrunkardsWalkBuilder {
+    map: Map,
+    starting_position: Position,
+    spawn_regions: Vec<Vec<usize>>,
+}
+
+impl MapBuilder for DrunkardsWalkBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        let start_index = self
+            .map
+            .map_index(self.starting_position.x, self.starting_position.y);
+
+        for area in self.spawn_regions.iter() {
+            if !area.contains(&start_index) {
+                spawner::spawn_region(ecs, &self.map, area, self.map.depth);
+            }
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position
+    }
+}
+
+impl DrunkardsWalkBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> DrunkardsWalkBuilder {
+        DrunkardsWalkBuilder {
+            map: Map::new(width, height, new_depth),
+            starting_position: Position { x: 0, y: 0 },
+            spawn_regions: Vec::new(),
+        }
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        self.starting_position = Position {
+            x: self.map.width / 2,
+            y: self.map.height / 2,
+        };
+        let start_index = self
+            .map
+            .map_index(self.starting_position.x, self.starting_position.y);
+        self.map.tiles[start_index] = TileType::Floor;
+
+        // The digger stays between 2 and width - 2 (height - 2), which on
+        // small maps is less than the floor asked for.
+        let carvable =
+            (i32::max(self.map.width - 3, 1) * i32::max(self.map.height - 3, 1)) as usize;
+        let desired_floor_tiles = usize::min(self.map.tiles.len() * FLOOR_PERCENT / 100, carvable);
+        let mut floor_tile_count = 1;
+
+        // Every digger leaves from the start and stumbles around
+        // until it dies of old age, so everything it digs is connected.
+        while floor_tile_count < desired_floor_tiles {
+            let mut digger_x = self.starting_position.x;
+            let mut digger_y = self.starting_position.y;
+
+            for _ in 0..DRUNKEN_LIFETIME {
+                let digger_index = self.map.map_index(digger_x, digger_y);
+                if self.map.tiles[digger_index] == TileType::Wall {
+                    self.map.tiles[digger_index] = TileType::Floor;
+                    floor_tile_count += 1;
+                }
+
+                match rng.roll_dice(1, 4) {
+                    1 if digger_x > 2 => digger_x -= 1,
+                    2 if digger_x < self.map.width - 2 => digger_x += 1,
+                    3 if digger_y > 2 => digger_y -= 1,
+                    4 if digger_y < self.map.height - 2 => digger_y += 1,
+                    _ => {}
+                }
+            }
+        }
+
+        let exit_index =
+            remove_unreachable_areas_returning_most_distant(&mut self.map, start_index);
+        self.map.tiles[exit_index] = TileType::DownStairs;
+
+        self.spawn_regions = generate_regions(&self.map);
+    }
+}
@@ -0,0 +1,104 @@
+use super::{
+    find_central_floor, generate_regions, remove_unreachable_areas_returning_most_distant,
+    MapBuilder,
+};
+use crate::{spawner, Map, Position, TileType};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+pub struct MazeBuilder {
+    map: Map,
+    starting_position: Position,
+    spawn_regions: Vec<Vec<usize>>,
+}
+
+impl MapBuilder for MazeBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        let start_index = self
+            .map
+            .map_index(self.starting_position.x, self.starting_position.y);
+
+        for area in self.spawn_regions.iter() {
+            if !area.contains(&start_index) {
+                spawner::spawn_region(ecs, &self.map, area, self.map.depth);
+            }
+        }
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position
+    }
+}
+
+impl MazeBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> MazeBuilder {
+        MazeBuilder {
+            map: Map::new(width, height, new_depth),
+            starting_position: Position { x: 0, y: 0 },
+            spawn_regions: Vec::new(),
+        }
+    }
+
+    // Recursive backtracker. Maze cells sit on odd map coordinates,
+    // the even coordinates between them are the walls we knock down.
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        let cells_wide = (self.map.width - 1) / 2;
+        let cells_high = (self.map.height - 1) / 2;
+        let mut visited = vec![false; (cells_wide * cells_high) as usize];
+        let mut stack: Vec<(i32, i32)> = vec![(0, 0)];
+
+        visited[0] = true;
+        self.carve(1, 1);
+
+        while let Some(&(cell_x, cell_y)) = stack.last() {
+            let neighbours: Vec<(i32, i32)> = [(0, -1), (1, 0), (0, 1), (-1, 0)]
+                .iter()
+                .map(|(dx, dy)| (cell_x + dx, cell_y + dy))
+                .filter(|(x, y)| {
+                    *x >= 0
+                        && *x < cells_wide
+                        && *y >= 0
+                        && *y < cells_high
+                        && !visited[(y * cells_wide + x) as usize]
+                })
+                .collect();
+
+            if neighbours.is_empty() {
+                stack.pop();
+                continue;
+            }
+
+            let (next_x, next_y) = neighbours[rng.range(0, neighbours.len() as i32) as usize];
+            visited[(next_y * cells_wide + next_x) as usize] = true;
+
+            // Knock down the wall between both cells, then the next cell itself.
+            self.carve(cell_x + next_x + 1, cell_y + next_y + 1);
+            self.carve(next_x * 2 + 1, next_y * 2 + 1);
+
+            stack.push((next_x, next_y));
+        }
+
+        self.starting_position = find_central_floor(&self.map);
+        let start_index = self
+            .map
+            .map_index(self.starting_position.x, self.starting_position.y);
+        let exit_index =
+            remove_unreachable_areas_returning_most_distant(&mut self.map, start_index);
+        self.map.tiles[exit_index] = TileType::DownStairs;
+
+        self.spawn_regions = generate_regions(&self.map);
+    }
+
+    fn carve(&mut self, x: i32, y: i32) {
+        let index = self.map.map_index(x, y);
+        self.map.tiles[index] = TileType::Floor;
+    }
+}
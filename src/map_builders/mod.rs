@@ -0,0 +1,43 @@
+use crate::{Map, Position};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+mod common;
+use common::*;
+
+mod simple_map;
+use simple_map::SimpleMapBuilder;
+
+mod bsp_dungeon;
+use bsp_dungeon::BspDungeonBuilder;
+
+mod cellular_automata;
+use cellular_automata::CellularAutomataBuilder;
+
+mod drunkard;
+use drunkard::DrunkardsWalkBuilder;
+
+mod maze;
+use maze::MazeBuilder;
+
+pub trait MapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator);
+    fn spawn_entities(&mut self, ecs: &mut World);
+    fn get_map(&self) -> Map;
+    fn get_starting_position(&self) -> Position;
+}
+
+pub fn random_builder(
+    new_depth: i32,
+    width: i32,
+    height: i32,
+    rng: &mut RandomNumberGenerator,
+) -> Box<dyn MapBuilder> {
+    match rng.roll_dice(1, 5) {
+        1 => Box::new(BspDungeonBuilder::new(new_depth, width, height)),
+        2 => Box::new(CellularAutomataBuilder::new(new_depth, width, height)),
+        3 => Box::new(DrunkardsWalkBuilder::new(new_depth, width, height)),
+        4 => Box::new(MazeBuilder::new(new_depth, width, height)),
+        _ => Box::new(SimpleMapBuilder::new(new_depth, width, height)),
+    }
+}
@@ -0,0 +1,116 @@
+use super::{
+    apply_horizontal_tunnel, apply_room_to_map, apply_vertical_tunnel, fallback_room,
+    find_doorways, MapBuilder,
+};
+use crate::{spawner, Map, Position, Rect, TileType};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+pub struct SimpleMapBuilder {
+    map: Map,
+    starting_position: Position,
+    doorways: Vec<usize>,
+}
+
+impl MapBuilder for SimpleMapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.rooms_and_corridors(rng);
+    }
+
+    fn spawn_entities(&mut self, ecs: &mut World) {
+        for room in self.map.rooms.iter().skip(1) {
+            spawner::spawn_room(ecs, &self.map, room, self.map.depth);
+        }
+        spawner::spawn_doors(ecs, &self.map, &self.doorways, &self.map.rooms[0]);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        self.starting_position
+    }
+}
+
+impl SimpleMapBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> SimpleMapBuilder {
+        SimpleMapBuilder {
+            map: Map::new(width, height, new_depth),
+            starting_position: Position { x: 0, y: 0 },
+            doorways: Vec::new(),
+        }
+    }
+
+    fn rooms_and_corridors(&mut self, rng: &mut RandomNumberGenerator) {
+        // Rooms shrink on small maps so they still fit inside the outer wall.
+        // Maps too small for any room only get the fallback one.
+        let max_rooms = if self.map.width > 2 && self.map.height > 2 {
+            30
+        } else {
+            0
+        };
+        let max_w: i32 = i32::min(10, self.map.width - 1);
+        let max_h: i32 = i32::min(10, self.map.height - 1);
+        let min_w: i32 = i32::min(6, max_w - 1);
+        let min_h: i32 = i32::min(6, max_h - 1);
+
+        for _ in 0..max_rooms {
+            let w = rng.range(min_w, max_w);
+            let h = rng.range(min_h, max_h);
+            let x = rng.roll_dice(1, self.map.width - w - 1) - 1;
+            let y = rng.roll_dice(1, self.map.height - h - 1) - 1;
+
+            let new_room = Rect::new(x, y, w, h);
+            let mut ok = true;
+            for other_room in self.map.rooms.iter() {
+                if new_room.intersect(other_room) {
+                    ok = false
+                }
+            }
+
+            if ok {
+                apply_room_to_map(&mut self.map, &new_room);
+
+                // 50% chance to make a vertical tunnel first and then a
+                // horizontal tunnel or vice-versa. This is because
+                // if you have two squares that are distant from each other
+                // you have two ways of connecting them (both ways form an L shape).
+                // The randomness makes it less repetitive.
+                if !self.map.rooms.is_empty() {
+                    let (new_x, new_y) = new_room.center();
+                    let (prev_x, prev_y) = self.map.rooms[self.map.rooms.len() - 1].center();
+                    apply_horizontal_tunnel(&mut self.map, prev_x, new_x, prev_y);
+                    apply_vertical_tunnel(&mut self.map, prev_y, new_y, new_x);
+                    if rng.range(0, 2) == 1 {
+                        apply_horizontal_tunnel(&mut self.map, prev_x, new_x, prev_y);
+                        apply_vertical_tunnel(&mut self.map, prev_y, new_y, new_x);
+                    } else {
+                        apply_vertical_tunnel(&mut self.map, prev_y, new_y, prev_x);
+                        apply_horizontal_tunnel(&mut self.map, prev_x, new_x, new_y);
+                    }
+                }
+
+                self.map.rooms.push(new_room);
+            }
+        }
+
+        if self.map.rooms.is_empty() {
+            let room = fallback_room(&self.map);
+            apply_room_to_map(&mut self.map, &room);
+            self.map.rooms.push(room);
+        }
+
+        self.doorways = find_doorways(&self.map);
+
+        let (stairs_x, stairs_y) = self.map.rooms[self.map.rooms.len() - 1].center();
+        let stairs_index = self.map.map_index(stairs_x, stairs_y);
+        self.map.tiles[stairs_index] = TileType::DownStairs;
+
+        let (start_x, start_y) = self.map.rooms[0].center();
+        self.starting_position = Position {
+            x: start_x,
+            y: start_y,
+        };
+    }
+}
@@ -1,5 +1,5 @@
 #![allow(unused)]
-use super::{BlocksTile, Map, Position};
+use super::{BlocksTile, BlocksVisibility, Door, Map, Position};
 use specs::prelude::*;
 
 pub struct MapIndexingSystem {}
@@ -9,11 +9,13 @@ impl<'a> System<'a> for MapIndexingSystem {
         WriteExpect<'a, Map>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, BlocksTile>,
+        ReadStorage<'a, BlocksVisibility>,
+        ReadStorage<'a, Door>,
         Entities<'a>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, position, blockers, entities) = data;
+        let (mut map, position, blockers, view_blockers, doors, entities) = data;
 
         map.populate_blocked();
         map.clear_content_index();
@@ -25,6 +27,16 @@ impl<'a> System<'a> for MapIndexingSystem {
                 map.blocked[idx] = true;
             }
 
+            if view_blockers.get(entity).is_some() {
+                map.view_blocked[idx] = true;
+            }
+
+            if let Some(door) = doors.get(entity) {
+                if !door.open && !door.locked {
+                    map.closed_doors[idx] = true;
+                }
+            }
+
             map.tile_content[idx].push(entity);
         }
     }
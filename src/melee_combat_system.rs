@@ -1,6 +1,8 @@
-use crate::GameLog;
+use crate::{GameLog, LogCategory, LogEntry, Renderable, RunStats};
 
-use super::{CombatStats, Name, SufferDamage, WantsToMelee};
+use super::{
+    CombatStats, DefenseBonus, Equipped, MeleePowerBonus, Name, SufferDamage, WantsToMelee,
+};
 use specs::prelude::*;
 
 pub struct MeleeCombatSystem {}
@@ -13,16 +15,43 @@ impl<'a> System<'a> for MeleeCombatSystem {
         ReadStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
         WriteExpect<'a, GameLog>,
+        ReadStorage<'a, MeleePowerBonus>,
+        ReadStorage<'a, DefenseBonus>,
+        ReadStorage<'a, Equipped>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, RunStats>,
+        ReadStorage<'a, Renderable>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut wants_to_melee, names, combat_stats, mut inflict_damage, mut gamelog) =
-            data;
+        let (
+            entities,
+            mut wants_to_melee,
+            names,
+            combat_stats,
+            mut inflict_damage,
+            mut gamelog,
+            melee_power_bonuses,
+            defense_bonuses,
+            equipped,
+            player_entity,
+            mut run_stats,
+            renderables,
+        ) = data;
 
-        for (_entity, wants_to_melee, name, stats) in
+        for (entity, wants_to_melee, name, stats) in
             (&entities, &wants_to_melee, &names, &combat_stats).join()
         {
             if stats.hp > 0 {
+                let mut offensive_bonus = 0;
+                for (_item, power_bonus, equipped_by) in
+                    (&entities, &melee_power_bonuses, &equipped).join()
+                {
+                    if equipped_by.owner == entity {
+                        offensive_bonus += power_bonus.power;
+                    }
+                }
+
                 let target_stats = match combat_stats.get(wants_to_melee.target) {
                     Some(target_stats) => target_stats,
                     None => return,
@@ -31,19 +60,49 @@ impl<'a> System<'a> for MeleeCombatSystem {
                 if target_stats.hp > 0 {
                     let target_name = names.get(wants_to_melee.target).unwrap();
 
-                    let damage = i32::max(0, stats.power - target_stats.defense);
+                    let mut defensive_bonus = 0;
+                    for (_item, defense_bonus, equipped_by) in
+                        (&entities, &defense_bonuses, &equipped).join()
+                    {
+                        if equipped_by.owner == wants_to_melee.target {
+                            defensive_bonus += defense_bonus.defense;
+                        }
+                    }
+
+                    let damage = i32::max(
+                        0,
+                        (stats.power + offensive_bonus) - (target_stats.defense + defensive_bonus),
+                    );
 
+                    let attacker = renderables.get(entity);
+                    let target = renderables.get(wants_to_melee.target);
                     if damage == 0 {
-                        let unable_hit_message =
-                            format!("{} is unable to hurt {}", &name.name, &target_name.name);
-                        gamelog.entries.push(unable_hit_message);
+                        gamelog.log(
+                            LogEntry::new(LogCategory::Combat)
+                                .name(&name.name, attacker)
+                                .text(" is unable to hurt ")
+                                .name(&target_name.name, target),
+                        );
                     } else {
-                        let hit_message = format!(
-                            "{} hits {}, for {} hp.",
-                            &name.name, &target_name.name, damage
+                        gamelog.log(
+                            LogEntry::new(LogCategory::Combat)
+                                .name(&name.name, attacker)
+                                .text(" hits ")
+                                .name(&target_name.name, target)
+                                .text(", for ")
+                                .damage(damage)
+                                .text(" hp."),
                         );
-                        gamelog.entries.push(hit_message);
-                        SufferDamage::new_damage(&mut inflict_damage, wants_to_melee.target, damage)
+                        SufferDamage::new_damage(
+                            &mut inflict_damage,
+                            wants_to_melee.target,
+                            damage,
+                            Some(entity),
+                        );
+
+                        if wants_to_melee.target == *player_entity {
+                            run_stats.last_hurt_by = name.name.clone();
+                        }
                     }
                 }
             }
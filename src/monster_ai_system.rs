@@ -1,7 +1,10 @@
-use crate::{Name, RunState, WantsToMelee};
+use crate::{
+    Asleep, Confusion, Door, EntityMoved, EntryTrigger, Hidden, MyTurn, Name, RunState,
+    WantsToMelee, WantsToOpenDoor,
+};
 
-use super::{Map, Monster, Position, Viewshed};
-use rltk::{console, Point};
+use super::{Map, Monster, Position, TrapAwareMap, Viewshed};
+use rltk::{console, Point, RandomNumberGenerator};
 use specs::prelude::*;
 
 pub struct MonsterAI {}
@@ -18,6 +21,15 @@ impl<'a> System<'a> for MonsterAI {
         ReadStorage<'a, Name>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, WantsToMelee>,
+        ReadStorage<'a, Confusion>,
+        ReadStorage<'a, Asleep>,
+        ReadStorage<'a, MyTurn>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        ReadStorage<'a, Door>,
+        WriteStorage<'a, WantsToOpenDoor>,
+        WriteStorage<'a, EntityMoved>,
+        ReadStorage<'a, EntryTrigger>,
+        ReadStorage<'a, Hidden>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -32,15 +44,78 @@ impl<'a> System<'a> for MonsterAI {
             name,
             mut position,
             mut wants_to_melee,
+            confused,
+            asleep,
+            turns,
+            mut rng,
+            doors,
+            mut wants_to_open,
+            mut entity_moved,
+            triggers,
+            hidden,
         ) = data;
 
-        if *runstate != RunState::MonsterTurn {
+        // Turns are only cleared when the next tick starts, so a monster that
+        // acted alongside the player must not act again on the player's turn.
+        if *runstate != RunState::Ticking {
             return;
         }
 
-        for (entity, viewshed, _monster, name, pos) in
-            (&entities, &mut viewshed, &mut monster, &name, &mut position).join()
+        // Only monsters whose turn it is get to act.
+        for (entity, viewshed, _monster, name, pos, _turn) in (
+            &entities,
+            &mut viewshed,
+            &mut monster,
+            &name,
+            &mut position,
+            &turns,
+        )
+            .join()
         {
+            if asleep.get(entity).is_some() {
+                continue;
+            }
+
+            // Traps out in the open are remembered, and spent ones forgotten.
+            let is_trap = |index: usize| {
+                map.tile_content[index]
+                    .iter()
+                    .any(|e| triggers.get(*e).is_some() && hidden.get(*e).is_none())
+            };
+            let known_before = _monster.known_traps.len();
+            _monster.known_traps.retain(|index| is_trap(*index));
+            for tile in viewshed.visible_tiles.iter() {
+                let index = map.map_index(tile.x, tile.y);
+                if is_trap(index) && !_monster.known_traps.contains(&index) {
+                    _monster.known_traps.push(index);
+                }
+            }
+            if _monster.known_traps.len() != known_before {
+                _monster.last_pathfind = None;
+            }
+
+            // Confused monsters stumble in a random direction instead of acting.
+            if confused.get(entity).is_some() {
+                let new_x = pos.x + rng.range(-1, 2);
+                let new_y = pos.y + rng.range(-1, 2);
+                if new_x > 0 && new_x < map.width - 1 && new_y > 0 && new_y < map.height - 1 {
+                    let new_idx = map.map_index(new_x, new_y);
+                    if !map.blocked[new_idx] {
+                        let idx = map.map_index(pos.x, pos.y);
+                        map.blocked[idx] = false;
+                        pos.x = new_x;
+                        pos.y = new_y;
+                        map.blocked[new_idx] = true;
+                        viewshed.dirty = true;
+                        _monster.last_pathfind = None;
+                        entity_moved
+                            .insert(entity, EntityMoved {})
+                            .expect("Unable to insert marker");
+                    }
+                }
+                continue;
+            }
+
             let distance =
                 rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
 
@@ -71,14 +146,34 @@ impl<'a> System<'a> for MonsterAI {
                     || _monster.last_pathfind.as_mut().unwrap().steps.len() <= 1
                     || viewshed.visible_tiles.contains(&*player_pos)
                 {
-                    _monster.last_pathfind = Some(rltk::a_star_search(
-                        map.map_index(pos.x, pos.y) as i32,
-                        map.map_index(last_known_player_pos.x, last_known_player_pos.y) as i32,
-                        &mut *map,
-                    ));
+                    let goal = map.map_index(last_known_player_pos.x, last_known_player_pos.y);
+                    let path = rltk::a_star_search(
+                        map.map_index(pos.x, pos.y),
+                        goal,
+                        &TrapAwareMap {
+                            map: &map,
+                            known_traps: &_monster.known_traps,
+                            goal: Some(goal),
+                        },
+                    );
+                    _monster.last_pathfind = Some(path);
                 }
 
                 let path = _monster.last_pathfind.as_mut().unwrap();
+
+                // A closed door in the way is opened first, which takes the turn.
+                if path.success && path.steps.len() > 1 && map.closed_doors[path.steps[1]] {
+                    let door = map.tile_content[path.steps[1]]
+                        .iter()
+                        .find(|entity| doors.get(**entity).is_some());
+                    if let Some(door) = door {
+                        wants_to_open
+                            .insert(entity, WantsToOpenDoor { door: *door })
+                            .expect("Unable to insert intent");
+                    }
+                    continue;
+                }
+
                 if path.success && path.steps.len() > 1 {
                     // Monster will move, so his current location will not be blocked anymore.
                     let mut idx = map.map_index(pos.x, pos.y);
@@ -95,6 +190,9 @@ impl<'a> System<'a> for MonsterAI {
                     map.blocked[idx] = true;
 
                     viewshed.dirty = true;
+                    entity_moved
+                        .insert(entity, EntityMoved {})
+                        .expect("Unable to insert marker");
                     _monster.last_pathfind = Some(path.clone());
                 } else {
                     _monster.last_pathfind = None;
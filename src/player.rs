@@ -1,11 +1,42 @@
-use crate::{CombatStats, GodMode, Map, Name, RunState, Viewshed, WantsToMelee, GameLog, WantsToPickupItem, Item};
+use crate::{CombatStats, GodMode, Map, Name, RunState, Viewshed, WantsToMelee, GameLog, WantsToPickupItem, Item, TileType, Confusion, Asleep, InInventory, WantsToUseItem, WantsToDropItem, WantsToDestroyItem, RunStats, Door, WantsToOpenDoor, EntityMoved, Hidden, Ranged, TravelGoal, clicked_tile, start_travel, travel_step};
 
-use super::{Player, Position, State, MAP_HEIGHT, MAP_WIDTH};
-use rltk::{console, Point, Rltk, VirtualKeyCode};
+use super::{Player, Position, State};
+use rltk::{console, Point, RandomNumberGenerator, Rltk, VirtualKeyCode};
+use serde::{Deserialize, Serialize};
 use specs::prelude::*;
 use std::cmp::{max, min};
 
+// Eight directions a confused player can stagger in.
+const STAGGER_DIRECTIONS: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// How far around the player searching looks, and the odds of finding
+/// each hidden trap in that area.
+const SEARCH_RADIUS: i32 = 2;
+const SEARCH_ODDS: i32 = 2;
+
+fn stagger_if_confused(ecs: &World, delta_x: i32, delta_y: i32) -> (i32, i32) {
+    let player_entity = ecs.fetch::<Entity>();
+    let confused = ecs.read_storage::<Confusion>();
+
+    if confused.get(*player_entity).is_none() {
+        return (delta_x, delta_y);
+    }
+
+    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+    STAGGER_DIRECTIONS[rng.range(0, 8) as usize]
+}
+
 pub fn try_move_player(delta_x: i32, delta_y: i32, gs: &mut State) {
+    let (delta_x, delta_y) = stagger_if_confused(&gs.ecs, delta_x, delta_y);
     let mut positions = gs.ecs.write_storage::<Position>();
     let mut players = gs.ecs.write_storage::<Player>();
     let mut viewsheds = gs.ecs.write_storage::<Viewshed>();
@@ -15,6 +46,9 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, gs: &mut State) {
     let name = gs.ecs.read_storage::<Name>();
     let entities = gs.ecs.entities();
     let mut wants_to_melee = gs.ecs.write_storage::<WantsToMelee>();
+    let doors = gs.ecs.read_storage::<Door>();
+    let mut wants_to_open = gs.ecs.write_storage::<WantsToOpenDoor>();
+    let mut entity_moved = gs.ecs.write_storage::<EntityMoved>();
 
     for (entity, _player, pos, viewshed) in
         (&entities, &mut players, &mut positions, &mut viewsheds).join()
@@ -48,13 +82,31 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, gs: &mut State) {
                 }
                 _ => {}
             }
+
+            // Bumping into a closed door opens it instead of moving.
+            if let Some(door) = doors.get(*potential_target) {
+                if !door.open {
+                    wants_to_open
+                        .insert(
+                            entity,
+                            WantsToOpenDoor {
+                                door: *potential_target,
+                            },
+                        )
+                        .expect("Unable to insert intent");
+                    return;
+                }
+            }
         }
 
         if !map.blocked[destination_index] || godmode.0 {
-            pos.x = min(MAP_WIDTH - 1, max(0, pos.x + delta_x));
-            pos.y = min(MAP_HEIGHT - 1, max(0, pos.y + delta_y));
+            pos.x = min(map.width - 1, max(0, pos.x + delta_x));
+            pos.y = min(map.height - 1, max(0, pos.y + delta_y));
 
             viewshed.dirty = true;
+            entity_moved
+                .insert(entity, EntityMoved {})
+                .expect("Unable to insert marker");
 
             let mut ppos = gs.ecs.write_resource::<Point>();
             ppos.x = pos.x;
@@ -76,44 +128,281 @@ pub fn toggle_godmode(gs: &mut State) {
     }
 }
 
+/// Everything the player can do on their turn, whether it came from the
+/// keyboard, a menu or a script.
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum PlayerAction {
+    Move {
+        delta_x: i32,
+        delta_y: i32,
+    },
+    PickUp,
+    Descend,
+    /// Items are picked by their position in the backpack, as listed in the inventory menu.
+    UseItem {
+        slot: usize,
+        target: Option<Point>,
+    },
+    DropItem {
+        slot: usize,
+    },
+    DestroyItem {
+        slot: usize,
+    },
+    ToggleGodMode,
+    Search,
+}
+
+/// The player's backpack, in the order the item menus list it.
+pub fn player_backpack(ecs: &World) -> Vec<Entity> {
+    let player_entity = ecs.fetch::<Entity>();
+    let backpack = ecs.read_storage::<InInventory>();
+    let entities = ecs.entities();
+
+    (&entities, &backpack)
+        .join()
+        .filter(|(_entity, item)| item.owner == *player_entity)
+        .map(|(entity, _item)| entity)
+        .collect()
+}
+
+pub fn backpack_slot(ecs: &World, item: Entity) -> Option<usize> {
+    player_backpack(ecs)
+        .iter()
+        .position(|entity| *entity == item)
+}
+
+/// A sleeping player loses their turn.
+pub fn skip_turn_if_asleep(ecs: &World) -> bool {
+    let player_entity = ecs.fetch::<Entity>();
+    let asleep = ecs.read_storage::<Asleep>();
+    if asleep.get(*player_entity).is_none() {
+        return false;
+    }
+
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+    gamelog.system("You are asleep.");
+    true
+}
+
 pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
-    // Player movement
-    match ctx.key {
+    if !gs.is_player_turn() {
+        return RunState::Ticking;
+    }
+
+    if skip_turn_if_asleep(&gs.ecs) {
+        return RunState::PlayerTurn;
+    }
+
+    // Travel carries on by itself until any key or click interrupts it.
+    if gs.travel.is_some() {
+        if ctx.key.is_some() || ctx.left_click {
+            gs.travel = None;
+            return RunState::AwaitingInput;
+        }
+        return travel_step(gs);
+    }
+
+    if let Some(destination) = clicked_tile(&gs.ecs, ctx) {
+        return start_travel(gs, TravelGoal::Tile(destination));
+    }
+
+    let key = match ctx.key {
         None => return RunState::AwaitingInput, // Nothing happened
-        Some(key) => match key {
-            VirtualKeyCode::G => {
-                get_item(&mut gs.ecs)
+        Some(key) => key,
+    };
+
+    let action = match key {
+        VirtualKeyCode::I => return RunState::ShowInventory,
+        VirtualKeyCode::D => return RunState::ShowDropItem,
+        VirtualKeyCode::X => return RunState::ShowDestroyItem,
+        VirtualKeyCode::M => return RunState::ShowLog { offset: 0 },
+
+        // Debug view of what spawns on this level, for balancing
+        VirtualKeyCode::F1 => {
+            let depth = gs.ecs.fetch::<Map>().depth;
+            return RunState::ShowSpawnTables { depth };
+        }
+
+        // Save and quit to the main menu
+        VirtualKeyCode::Escape => return RunState::SaveGame,
+
+        VirtualKeyCode::G => PlayerAction::PickUp,
+        VirtualKeyCode::S => PlayerAction::Search,
+        VirtualKeyCode::O => return start_travel(gs, TravelGoal::Explore),
+
+        // Player movement
+        VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => {
+            PlayerAction::Move { delta_x: -1, delta_y: 0 }
+        }
+        VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => {
+            PlayerAction::Move { delta_x: 1, delta_y: 0 }
+        }
+        VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => {
+            PlayerAction::Move { delta_x: 0, delta_y: -1 }
+        }
+        VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => {
+            PlayerAction::Move { delta_x: 0, delta_y: 1 }
+        }
+
+        // Diagonals
+        VirtualKeyCode::Numpad9 | VirtualKeyCode::Y => {
+            PlayerAction::Move { delta_x: -1, delta_y: -1 }
+        }
+        VirtualKeyCode::Numpad7 | VirtualKeyCode::U => {
+            PlayerAction::Move { delta_x: 1, delta_y: -1 }
+        }
+        VirtualKeyCode::Numpad3 | VirtualKeyCode::N => {
+            PlayerAction::Move { delta_x: 1, delta_y: 1 }
+        }
+        VirtualKeyCode::Numpad1 | VirtualKeyCode::B => {
+            PlayerAction::Move { delta_x: -1, delta_y: 1 }
+        }
+        VirtualKeyCode::Key0 => PlayerAction::ToggleGodMode,
+
+        // Level changes
+        VirtualKeyCode::Period => PlayerAction::Descend,
+        _ => return RunState::AwaitingInput,
+    };
+
+    perform_action(gs, action)
+}
+
+/// Carries out `action` for the player and returns the next run state.
+pub fn perform_action(gs: &mut State, action: PlayerAction) -> RunState {
+    if let Some(recorder) = gs.recorder.as_mut() {
+        let turn = gs.ecs.fetch::<RunStats>().turns;
+        recorder.record(turn, action);
+    }
+
+    match action {
+        PlayerAction::Move { delta_x, delta_y } => try_move_player(delta_x, delta_y, gs),
+        PlayerAction::PickUp => get_item(&mut gs.ecs),
+        PlayerAction::Descend => {
+            if try_next_level(&mut gs.ecs) {
+                return RunState::NextLevel;
             }
-            VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => {
-                try_move_player(-1, 0, gs)
+            return RunState::AwaitingInput;
+        }
+        PlayerAction::UseItem { slot, target } => {
+            let item = match player_backpack(&gs.ecs).get(slot) {
+                Some(item) => *item,
+                None => return RunState::AwaitingInput,
+            };
+            if let Some(target) = target {
+                if !is_valid_target(&gs.ecs, item, target) {
+                    let mut gamelog = gs.ecs.fetch_mut::<GameLog>();
+                    gamelog.system("You can't target that.");
+                    return RunState::AwaitingInput;
+                }
             }
+            let player_entity = *gs.ecs.fetch::<Entity>();
+            let mut intent = gs.ecs.write_storage::<WantsToUseItem>();
+            intent
+                .insert(player_entity, WantsToUseItem { item, target })
+                .expect("Unable to insert intent");
+        }
+        PlayerAction::DropItem { slot } => {
+            let item = match player_backpack(&gs.ecs).get(slot) {
+                Some(item) => *item,
+                None => return RunState::AwaitingInput,
+            };
+            let player_entity = *gs.ecs.fetch::<Entity>();
+            let mut intent = gs.ecs.write_storage::<WantsToDropItem>();
+            intent
+                .insert(player_entity, WantsToDropItem { item })
+                .expect("Unable to insert intent");
+        }
+        PlayerAction::DestroyItem { slot } => {
+            let item = match player_backpack(&gs.ecs).get(slot) {
+                Some(item) => *item,
+                None => return RunState::AwaitingInput,
+            };
+            let player_entity = *gs.ecs.fetch::<Entity>();
+            let mut intent = gs.ecs.write_storage::<WantsToDestroyItem>();
+            intent
+                .insert(player_entity, WantsToDestroyItem { item })
+                .expect("Unable to insert intent");
+        }
+        PlayerAction::ToggleGodMode => toggle_godmode(gs),
+        PlayerAction::Search => search(&mut gs.ecs),
+    }
 
-            VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => {
-                try_move_player(1, 0, gs)
-            }
+    RunState::PlayerTurn
+}
 
-            VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => {
-                try_move_player(0, -1, gs)
-            }
+/// Scripted actions don't go through the targeting cursor, so its limits
+/// are checked again here: a ranged item, aimed at a tile the player can
+/// see and within range.
+fn is_valid_target(ecs: &World, item: Entity, target: Point) -> bool {
+    let range = match ecs.read_storage::<Ranged>().get(item) {
+        Some(ranged) => ranged.range,
+        None => return false,
+    };
 
-            VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => {
-                try_move_player(0, 1, gs)
-            }
+    let map = ecs.fetch::<Map>();
+    if target.x < 0 || target.x >= map.width || target.y < 0 || target.y >= map.height {
+        return false;
+    }
 
-            // Diagonals
-            VirtualKeyCode::Numpad9 | VirtualKeyCode::Y => try_move_player(-1, -1, gs),
+    let player_entity = ecs.fetch::<Entity>();
+    let player_pos = ecs.fetch::<Point>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    let visible = viewsheds
+        .get(*player_entity)
+        .is_some_and(|viewshed| viewshed.visible_tiles.contains(&target));
 
-            VirtualKeyCode::Numpad7 | VirtualKeyCode::U => try_move_player(1, -1, gs),
+    visible && rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, target) <= range as f32
+}
 
-            VirtualKeyCode::Numpad3 | VirtualKeyCode::N => try_move_player(1, 1, gs),
+pub fn try_next_level(ecs: &mut World) -> bool {
+    let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let player_index = map.map_index(player_pos.x, player_pos.y);
 
-            VirtualKeyCode::Numpad1 | VirtualKeyCode::B => try_move_player(-1, 1, gs),
-            VirtualKeyCode::Key0 => toggle_godmode(gs),
-            _ => return RunState::AwaitingInput,
-        },
+    if map.tiles[player_index] == TileType::DownStairs {
+        true
+    } else {
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        gamelog.system("There is no way down from here.");
+        false
     }
+}
 
-    RunState::PlayerTurn
+/// Looks over the tiles around the player, with a good chance of finding
+/// each hidden trap there.
+fn search(ecs: &mut World) {
+    let player_pos = *ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let names = ecs.read_storage::<Name>();
+    let mut hidden = ecs.write_storage::<Hidden>();
+    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let mut found = false;
+    for y in player_pos.y - SEARCH_RADIUS..=player_pos.y + SEARCH_RADIUS {
+        for x in player_pos.x - SEARCH_RADIUS..=player_pos.x + SEARCH_RADIUS {
+            if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                continue;
+            }
+            let index = map.map_index(x, y);
+            if !map.visible_tiles[index] {
+                continue;
+            }
+
+            for entity in map.tile_content[index].iter() {
+                if hidden.get(*entity).is_some() && rng.roll_dice(1, SEARCH_ODDS) == 1 {
+                    hidden.remove(*entity);
+                    gamelog.system(format!("You find a {}!", names.get(*entity).unwrap().name));
+                    found = true;
+                }
+            }
+        }
+    }
+
+    if !found {
+        gamelog.system("You search, but find nothing.");
+    }
 }
 
 fn get_item(ecs: &mut World) {
@@ -132,7 +421,7 @@ fn get_item(ecs: &mut World) {
     }
 
     match target_item {
-        None => gamelog.entries.push("There is nothing here to pickup.".to_string()),
+        None => gamelog.system("There is nothing here to pickup."),
         Some(item) => {
             let mut pickup = ecs.write_storage::<WantsToPickupItem>();
             pickup.insert(*player_entity, WantsToPickupItem{ collected_by: *player_entity, item }).expect("Unable to insert want to pickup");
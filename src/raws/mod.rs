@@ -0,0 +1,219 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::EquipmentSlot;
+use rltk::{FontCharType, RGB};
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+
+mod rawmaster;
+pub use rawmaster::*;
+
+/// Monster and item definitions and where they spawn, read once when the
+/// game starts. Looked for next to the executable, then in the current
+/// directory, so they can be tweaked without a rebuild.
+pub const RAWS_FILE: &str = "raws/spawns.json";
+/// The raws as they were at build time, only used when no file is found.
+const BUILTIN_RAWS: &str = include_str!("../../raws/spawns.json");
+
+static RAWS: OnceLock<RawMaster> = OnceLock::new();
+
+/// Reads, parses and validates the raws, panicking with the file and line
+/// of the first problem found. Later calls return the same definitions.
+pub fn load_raws() -> &'static RawMaster {
+    RAWS.get_or_init(|| match find_raws() {
+        Some(path) => read_raws(&path),
+        None => RawMaster::load(RAWS_FILE, BUILTIN_RAWS),
+    })
+}
+
+/// Like `load_raws`, but reads them from `path` instead of looking for
+/// `RAWS_FILE`. Does nothing if the raws are already loaded.
+pub fn load_raws_from(path: &Path) -> &'static RawMaster {
+    RAWS.get_or_init(|| read_raws(path))
+}
+
+fn find_raws() -> Option<PathBuf> {
+    let beside_executable = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(RAWS_FILE)));
+    beside_executable
+        .into_iter()
+        .chain(Some(PathBuf::from(RAWS_FILE)))
+        .find(|path| path.is_file())
+}
+
+fn read_raws(path: &Path) -> RawMaster {
+    let contents = fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("Unable to read the raws from {}: {}", path.display(), e));
+    RawMaster::load(&path.display().to_string(), &contents)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Raws {
+    pub monsters: Vec<MonsterRaw>,
+    pub items: Vec<ItemRaw>,
+    pub props: Vec<PropRaw>,
+    pub monster_spawns: Vec<SpawnRaw>,
+    pub item_spawns: Vec<SpawnRaw>,
+    pub trap_spawns: Vec<SpawnRaw>,
+}
+
+/// An entry of a spawn table, only rolled for between `min_depth` and
+/// `max_depth`. Leaving out `max_depth` keeps it around all the way down.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnRaw {
+    pub name: String,
+    pub weight: i32,
+    #[serde(default = "first_level")]
+    pub min_depth: i32,
+    pub max_depth: Option<i32>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RenderableRaw {
+    #[serde(deserialize_with = "glyph")]
+    pub glyph: FontCharType,
+    #[serde(deserialize_with = "color")]
+    pub fg: RGB,
+    #[serde(deserialize_with = "color", default = "black")]
+    pub bg: RGB,
+}
+
+/// A monster as it appears on the first level. Deeper levels toughen it up.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MonsterRaw {
+    pub name: String,
+    pub renderable: RenderableRaw,
+    pub vision_range: i32,
+    pub speed: i32,
+    pub stats: MonsterStatsRaw,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MonsterStatsRaw {
+    pub max_hp: i32,
+    pub defense: i32,
+    pub power: i32,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ItemRaw {
+    pub name: String,
+    pub renderable: RenderableRaw,
+    #[serde(default)]
+    pub consumable: bool,
+    #[serde(default)]
+    pub effects: ItemEffectsRaw,
+    pub equippable: Option<EquippableRaw>,
+    /// Unlocks a locked door, and is used up doing so.
+    #[serde(default)]
+    pub key: bool,
+}
+
+/// What using an item does. Everything but healing and food needs a
+/// `ranged` target.
+#[derive(Deserialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ItemEffectsRaw {
+    pub healing: Option<i32>,
+    #[serde(default)]
+    pub food: bool,
+    pub ranged: Option<i32>,
+    pub damage: Option<i32>,
+    pub area_of_effect: Option<i32>,
+    pub confusion: Option<i32>,
+    pub poison: Option<PoisonRaw>,
+    pub sleep: Option<i32>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PoisonRaw {
+    pub damage: i32,
+    pub turns: i32,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EquippableRaw {
+    pub slot: EquipmentSlot,
+    #[serde(default)]
+    pub power_bonus: i32,
+    #[serde(default)]
+    pub defense_bonus: i32,
+}
+
+/// Scenery that isn't picked up, like doors and traps.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PropRaw {
+    pub name: String,
+    pub renderable: RenderableRaw,
+    #[serde(default)]
+    pub blocks_tile: bool,
+    #[serde(default)]
+    pub blocks_visibility: bool,
+    /// Left off the map until someone finds it.
+    #[serde(default)]
+    pub hidden: bool,
+    pub door: Option<DoorRaw>,
+    pub entry_trigger: Option<EntryTriggerRaw>,
+}
+
+/// Doors start closed. A locked one takes a key to open.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DoorRaw {
+    #[serde(default)]
+    pub locked: bool,
+}
+
+/// What happens to whoever steps on a trap. Unless it is a
+/// `single_activation` trap, it goes off every time.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct EntryTriggerRaw {
+    pub damage: Option<i32>,
+    pub poison: Option<PoisonRaw>,
+    #[serde(default)]
+    pub teleports: bool,
+    #[serde(default)]
+    pub single_activation: bool,
+}
+
+/// Glyphs are written as a one character string, `"o"`.
+fn glyph<'de, D: Deserializer<'de>>(deserializer: D) -> Result<FontCharType, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(rltk::to_cp437(c)),
+        _ => Err(de::Error::custom(format!(
+            "glyph must be a single character, got {:?}",
+            text
+        ))),
+    }
+}
+
+/// Colours are written in HTML notation, `"#FF0000"`.
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<RGB, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    RGB::from_hex(&text)
+        .map_err(|_| de::Error::custom(format!("invalid colour {:?}, expected \"#RRGGBB\"", text)))
+}
+
+fn first_level() -> i32 {
+    1
+}
+
+fn black() -> RGB {
+    RGB::named(rltk::BLACK)
+}
@@ -0,0 +1,470 @@
+use std::collections::HashMap;
+
+use super::{load_raws, ItemRaw, MonsterRaw, PropRaw, Raws, RenderableRaw, SpawnRaw};
+use crate::{
+    AreaOfEffect, Asleep, BlocksTile, BlocksVisibility, CombatStats, Confusion, Consumable,
+    DefenseBonus, Door, EntryTrigger, Equippable, Experience, Hidden, InflictsDamage, Initiative,
+    Item, Key, LastPathUpdate, MeleePowerBonus, Monster, Name, Poisoned, Poisoner, Position,
+    Potion, ProvidesFood, Ranged, Renderable, SerializeMe, SingleActivation, SpawnTable, Teleports,
+    Viewshed,
+};
+use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
+
+/// The parsed raws, indexed by name.
+pub struct RawMaster {
+    raws: Raws,
+    monster_index: HashMap<String, usize>,
+    item_index: HashMap<String, usize>,
+    prop_index: HashMap<String, usize>,
+}
+
+impl RawMaster {
+    /// Parses the raws read from `path`, panicking with the line of the
+    /// first problem.
+    pub fn load(path: &str, contents: &str) -> RawMaster {
+        RawMaster::parse(contents)
+            .unwrap_or_else(|(line, error)| panic!("{}:{}: invalid raws: {}", path, line, error))
+    }
+
+    /// Reads the raws from `contents`, or returns the line of the first
+    /// problem and what is wrong there.
+    pub fn parse(contents: &str) -> Result<RawMaster, (usize, String)> {
+        let raws: Raws = serde_json::from_str(contents).map_err(|e| (e.line(), e.to_string()))?;
+
+        let mut master = RawMaster {
+            raws,
+            monster_index: HashMap::new(),
+            item_index: HashMap::new(),
+            prop_index: HashMap::new(),
+        };
+        for (index, monster) in master.raws.monsters.iter().enumerate() {
+            master.monster_index.insert(monster.name.clone(), index);
+        }
+        for (index, item) in master.raws.items.iter().enumerate() {
+            master.item_index.insert(item.name.clone(), index);
+        }
+        for (index, prop) in master.raws.props.iter().enumerate() {
+            master.prop_index.insert(prop.name.clone(), index);
+        }
+        master.validate(contents)?;
+
+        Ok(master)
+    }
+
+    /// Checks what the schema alone can't: names are unique, stats and
+    /// effects make sense, and spawn tables only list what exists.
+    fn validate(&self, contents: &str) -> Result<(), (usize, String)> {
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        let names = self
+            .raws
+            .monsters
+            .iter()
+            .map(|monster| &monster.name)
+            .chain(self.raws.items.iter().map(|item| &item.name))
+            .chain(self.raws.props.iter().map(|prop| &prop.name));
+        for name in names {
+            let count = seen.entry(name).or_insert(0);
+            if *count > 0 {
+                return Err((
+                    line_of(contents, name, *count),
+                    format!("{:?} is defined more than once", name),
+                ));
+            }
+            *count += 1;
+        }
+
+        for monster in self.raws.monsters.iter() {
+            let line = line_of(contents, &monster.name, 0);
+            if monster.stats.max_hp < 1 {
+                return Err((line, format!("{:?} needs a max_hp above 0", monster.name)));
+            }
+            if monster.speed < 1 {
+                return Err((line, format!("{:?} needs a speed above 0", monster.name)));
+            }
+        }
+
+        for item in self.raws.items.iter() {
+            let effects = &item.effects;
+            let targeted = effects.damage.is_some()
+                || effects.area_of_effect.is_some()
+                || effects.confusion.is_some()
+                || effects.poison.is_some()
+                || effects.sleep.is_some();
+            if targeted && effects.ranged.is_none() {
+                return Err((
+                    line_of(contents, &item.name, 0),
+                    format!("{:?} has targeted effects but no range", item.name),
+                ));
+            }
+        }
+
+        let spawns = self
+            .raws
+            .monster_spawns
+            .iter()
+            .map(|spawn| (spawn, "monster"))
+            .chain(self.raws.item_spawns.iter().map(|spawn| (spawn, "item")))
+            .chain(self.raws.trap_spawns.iter().map(|spawn| (spawn, "trap")));
+        for (spawn, kind) in spawns {
+            // Spawn tables come last, so the entry is the last mention of its name.
+            let mentions = contents.matches(&format!("\"{}\"", spawn.name)).count();
+            let line = line_of(contents, &spawn.name, mentions.saturating_sub(1));
+
+            let defined = match kind {
+                "monster" => self.monster_index.contains_key(&spawn.name),
+                "item" => self.item_index.contains_key(&spawn.name),
+                _ => self
+                    .prop(&spawn.name)
+                    .is_some_and(|prop| prop.entry_trigger.is_some()),
+            };
+            if !defined {
+                return Err((line, format!("spawns unknown {} {:?}", kind, spawn.name)));
+            }
+            if spawn.weight < 1 {
+                return Err((line, format!("{:?} needs a weight above 0", spawn.name)));
+            }
+            if spawn.max_depth.is_some_and(|max| max < spawn.min_depth) {
+                return Err((
+                    line,
+                    format!("{:?} has a max_depth below its min_depth", spawn.name),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn monster(&self, name: &str) -> Option<&MonsterRaw> {
+        self.monster_index
+            .get(name)
+            .map(|index| &self.raws.monsters[*index])
+    }
+
+    pub fn item(&self, name: &str) -> Option<&ItemRaw> {
+        self.item_index
+            .get(name)
+            .map(|index| &self.raws.items[*index])
+    }
+
+    pub fn prop(&self, name: &str) -> Option<&PropRaw> {
+        self.prop_index
+            .get(name)
+            .map(|index| &self.raws.props[*index])
+    }
+
+    pub fn monster_spawn_table(&self, depth: i32) -> SpawnTable {
+        spawn_table(&self.raws.monster_spawns, depth)
+    }
+
+    pub fn item_spawn_table(&self, depth: i32) -> SpawnTable {
+        spawn_table(&self.raws.item_spawns, depth)
+    }
+
+    pub fn trap_spawn_table(&self, depth: i32) -> SpawnTable {
+        spawn_table(&self.raws.trap_spawns, depth)
+    }
+}
+
+/// Line of the `nth` mention of `name` as a string, to point validation
+/// errors at the entry they are about.
+fn line_of(contents: &str, name: &str, nth: usize) -> usize {
+    let quoted = format!("\"{}\"", name);
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.contains(&quoted))
+        .nth(nth)
+        .map_or(0, |(number, _)| number + 1)
+}
+
+fn spawn_table(spawns: &[SpawnRaw], depth: i32) -> SpawnTable {
+    spawns
+        .iter()
+        .filter(|spawn| depth >= spawn.min_depth && spawn.max_depth.is_none_or(|max| depth <= max))
+        .fold(SpawnTable::new(), |table, spawn| {
+            table.add(&spawn.name, spawn.weight)
+        })
+}
+
+fn renderable(raw: &RenderableRaw) -> Renderable {
+    Renderable {
+        glyph: raw.glyph,
+        fg: raw.fg,
+        bg: raw.bg,
+    }
+}
+
+/// Creates the monster, item or prop called `name` from the raws at `pos`.
+/// Returns `None` if the raws don't define it.
+pub fn spawn_named_entity(ecs: &mut World, name: &str, pos: Position) -> Option<Entity> {
+    let raws = load_raws();
+    if let Some(monster) = raws.monster(name) {
+        return Some(spawn_monster(ecs, monster, pos));
+    }
+    if let Some(item) = raws.item(name) {
+        return Some(spawn_item(ecs, item, pos));
+    }
+    if let Some(prop) = raws.prop(name) {
+        return Some(spawn_prop(ecs, prop, pos));
+    }
+    None
+}
+
+fn spawn_monster(ecs: &mut World, monster: &MonsterRaw, pos: Position) -> Entity {
+    ecs.create_entity()
+        .with(pos)
+        .with(renderable(&monster.renderable))
+        .with(Viewshed {
+            visible_tiles: Vec::new(),
+            range: monster.vision_range,
+            dirty: true,
+        })
+        .with(Monster {
+            last_known_player_pos: None,
+            last_pathfind: None,
+            next_step: 0,
+            known_traps: Vec::new(),
+        })
+        .with(Name {
+            name: monster.name.clone(),
+        })
+        .with(BlocksTile {})
+        .with(CombatStats {
+            max_hp: monster.stats.max_hp,
+            hp: monster.stats.max_hp,
+            defense: monster.stats.defense,
+            power: monster.stats.power,
+        })
+        .with(Experience { level: 1, xp: 0 })
+        .with(Initiative {
+            speed: monster.speed,
+            energy: 0,
+        })
+        .with(LastPathUpdate { nb_frames: 0 })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+fn spawn_item(ecs: &mut World, item: &ItemRaw, pos: Position) -> Entity {
+    let mut builder = ecs
+        .create_entity()
+        .with(pos)
+        .with(renderable(&item.renderable))
+        .with(Name {
+            name: item.name.clone(),
+        })
+        .with(Item {});
+
+    if item.consumable {
+        builder = builder.with(Consumable {});
+    }
+    if item.key {
+        builder = builder.with(Key {});
+    }
+
+    let effects = &item.effects;
+    if let Some(heal_amount) = effects.healing {
+        builder = builder.with(Potion { heal_amount });
+    }
+    if effects.food {
+        builder = builder.with(ProvidesFood {});
+    }
+    if let Some(range) = effects.ranged {
+        builder = builder.with(Ranged { range });
+    }
+    if let Some(damage) = effects.damage {
+        builder = builder.with(InflictsDamage { damage });
+    }
+    if let Some(radius) = effects.area_of_effect {
+        builder = builder.with(AreaOfEffect { radius });
+    }
+    if let Some(turns) = effects.confusion {
+        builder = builder.with(Confusion { turns });
+    }
+    if let Some(poison) = &effects.poison {
+        builder = builder.with(Poisoned {
+            dmg_per_turn: poison.damage,
+            turns: poison.turns,
+            source: Poisoner(None),
+        });
+    }
+    if let Some(turns) = effects.sleep {
+        builder = builder.with(Asleep { turns });
+    }
+
+    if let Some(equippable) = &item.equippable {
+        builder = builder.with(Equippable {
+            slot: equippable.slot,
+        });
+        if equippable.power_bonus != 0 {
+            builder = builder.with(MeleePowerBonus {
+                power: equippable.power_bonus,
+            });
+        }
+        if equippable.defense_bonus != 0 {
+            builder = builder.with(DefenseBonus {
+                defense: equippable.defense_bonus,
+            });
+        }
+    }
+
+    builder.marked::<SimpleMarker<SerializeMe>>().build()
+}
+
+fn spawn_prop(ecs: &mut World, prop: &PropRaw, pos: Position) -> Entity {
+    let mut builder = ecs
+        .create_entity()
+        .with(pos)
+        .with(renderable(&prop.renderable))
+        .with(Name {
+            name: prop.name.clone(),
+        });
+
+    if prop.blocks_tile {
+        builder = builder.with(BlocksTile {});
+    }
+    if prop.blocks_visibility {
+        builder = builder.with(BlocksVisibility {});
+    }
+    if prop.hidden {
+        builder = builder.with(Hidden {});
+    }
+    if let Some(door) = &prop.door {
+        builder = builder.with(Door {
+            open: false,
+            locked: door.locked,
+        });
+    }
+    if let Some(trigger) = &prop.entry_trigger {
+        builder = builder.with(EntryTrigger {});
+        if let Some(damage) = trigger.damage {
+            builder = builder.with(InflictsDamage { damage });
+        }
+        if let Some(poison) = &trigger.poison {
+            builder = builder.with(Poisoned {
+                dmg_per_turn: poison.damage,
+                turns: poison.turns,
+                source: Poisoner(None),
+            });
+        }
+        if trigger.teleports {
+            builder = builder.with(Teleports {});
+        }
+        if trigger.single_activation {
+            builder = builder.with(SingleActivation {});
+        }
+    }
+
+    builder.marked::<SimpleMarker<SerializeMe>>().build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{spawn_table, RawMaster};
+    use crate::SpawnRaw;
+    use rltk::RandomNumberGenerator;
+
+    const RAWS: &str = r##"{
+    "monsters": [
+        {
+            "name": "Orc",
+            "renderable": { "glyph": "o", "fg": "#FF0000" },
+            "vision_range": 8,
+            "speed": 7,
+            "stats": { "max_hp": 16, "defense": 1, "power": 4 }
+        }
+    ],
+    "items": [
+        {
+            "name": "Health Potion",
+            "renderable": { "glyph": "!", "fg": "#FF00FF" },
+            "consumable": true,
+            "effects": { "healing": 8 }
+        }
+    ],
+    "props": [],
+    "monster_spawns": [
+        { "name": "Orc", "weight": 10 }
+    ],
+    "item_spawns": [
+        { "name": "Health Potion", "weight": 7 }
+    ],
+    "trap_spawns": []
+}"##;
+
+    /// The line and message `parse` rejects `contents` with.
+    fn rejection(contents: &str) -> (usize, String) {
+        match RawMaster::parse(contents) {
+            Ok(_) => panic!("the raws were accepted"),
+            Err(error) => error,
+        }
+    }
+
+    #[test]
+    fn valid_raws_parse() {
+        let raws = RawMaster::parse(RAWS).unwrap_or_else(|(line, error)| panic!("{line}: {error}"));
+        assert!(raws.monster("Orc").is_some());
+        assert!(raws.item("Health Potion").is_some());
+    }
+
+    #[test]
+    fn malformed_json_reports_its_line() {
+        let (line, _) = rejection(&RAWS.replacen("\"speed\": 7,", "\"speed\": 7", 1));
+        assert_eq!(line, 8);
+    }
+
+    #[test]
+    fn unknown_field_reports_its_line() {
+        let (line, error) = rejection(&RAWS.replacen("\"speed\": 7,", "\"sped\": 7,", 1));
+        assert_eq!(line, 7);
+        assert!(error.contains("sped"), "{}", error);
+    }
+
+    #[test]
+    fn duplicate_name_reports_the_second_definition() {
+        let contents = RAWS.replacen("\"Health Potion\",", "\"Orc\",", 1);
+        let (line, error) = rejection(&contents);
+        assert_eq!(line, 13);
+        assert!(error.contains("more than once"), "{}", error);
+    }
+
+    #[test]
+    fn spawn_of_undefined_entity_reports_the_spawn_entry() {
+        let contents = RAWS.replacen(
+            "\"name\": \"Orc\", \"weight\"",
+            "\"name\": \"Troll\", \"weight\"",
+            1,
+        );
+        let (line, error) = rejection(&contents);
+        assert_eq!(line, 21);
+        assert!(error.contains("unknown monster \"Troll\""), "{}", error);
+    }
+
+    fn spawn(name: &str, min_depth: i32, max_depth: Option<i32>) -> SpawnRaw {
+        SpawnRaw {
+            name: name.to_string(),
+            weight: 10,
+            min_depth,
+            max_depth,
+        }
+    }
+
+    #[test]
+    fn spawns_only_roll_between_their_depths() {
+        let spawns = vec![spawn("Goblin", 1, Some(2)), spawn("Ogre", 4, None)];
+        let mut rng = RandomNumberGenerator::seeded(1);
+
+        for depth in 1..=6 {
+            let table = spawn_table(&spawns, depth);
+            for _ in 0..100 {
+                let expected = match depth {
+                    1..=2 => Some("Goblin"),
+                    3 => None,
+                    _ => Some("Ogre"),
+                };
+                assert_eq!(table.roll(&mut rng), expected, "at depth {}", depth);
+            }
+        }
+    }
+}
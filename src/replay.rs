@@ -0,0 +1,97 @@
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::Write;
+
+use crate::PlayerAction;
+use serde::{Deserialize, Serialize};
+
+/// Every run started from the main menu is recorded here, overwriting the
+/// previous one.
+pub const REPLAY_FILE: &str = "./replay.jsonl";
+
+/// First line of a replay file.
+#[derive(Serialize, Deserialize)]
+struct ReplayHeader {
+    seed: u64,
+}
+
+/// One line per action after the header.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct RecordedAction {
+    pub turn: i32,
+    pub action: PlayerAction,
+}
+
+/// A recorded run: the seed the world was built from and everything the
+/// player did in it.
+pub struct Replay {
+    pub seed: u64,
+    pub actions: Vec<RecordedAction>,
+}
+
+impl Replay {
+    pub fn load(path: &str) -> Replay {
+        let contents = fs::read_to_string(path).expect("Unable to read replay file");
+        let mut lines = contents.lines().enumerate();
+
+        let header: ReplayHeader = match lines.next() {
+            Some((_, line)) => serde_json::from_str(line)
+                .unwrap_or_else(|e| panic!("{}:1: invalid replay header: {}", path, e)),
+            None => panic!("{}: replay file is empty", path),
+        };
+
+        let actions = lines
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(number, line)| {
+                serde_json::from_str(line).unwrap_or_else(|e| {
+                    panic!("{}:{}: invalid replay action: {}", path, number + 1, e)
+                })
+            })
+            .collect();
+
+        Replay {
+            seed: header.seed,
+            actions,
+        }
+    }
+}
+
+/// Writes actions to the replay file as they happen, so a run that ends in
+/// a crash can still be replayed up to it.
+pub struct ReplayRecorder {
+    file: File,
+}
+
+impl ReplayRecorder {
+    pub fn create(path: &str, seed: u64) -> ReplayRecorder {
+        let mut recorder = ReplayRecorder {
+            file: File::create(path).expect("Unable to create replay file"),
+        };
+        recorder.write_line(&ReplayHeader { seed });
+        recorder
+    }
+
+    pub fn record(&mut self, turn: i32, action: PlayerAction) {
+        self.write_line(&RecordedAction { turn, action });
+    }
+
+    fn write_line<T: Serialize>(&mut self, line: &T) {
+        let json = serde_json::to_string(line).expect("Unable to serialize replay line");
+        writeln!(self.file, "{}", json).expect("Unable to write to replay file");
+    }
+}
+
+/// Actions still to be played back in `--replay` mode.
+pub struct ReplayPlayback {
+    pub actions: VecDeque<RecordedAction>,
+    pub fast_forward: bool,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> ReplayPlayback {
+        ReplayPlayback {
+            actions: replay.actions.into(),
+            fast_forward: false,
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::convert::Infallible;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+use super::components::*;
+use crate::{GameLog, GodMode, Map, RunSeed, RunState, RunStats};
+use rltk::Point;
+use specs::prelude::*;
+use specs::saveload::{
+    DeserializeComponents, MarkedBuilder, SerializeComponents, SimpleMarker, SimpleMarkerAllocator,
+};
+
+pub const SAVE_FILE: &str = "./savegame.json";
+
+macro_rules! serialize_individually {
+    ($ecs:expr, $ser:expr, $data:expr, $( $type:ty),*) => {
+        $(
+        SerializeComponents::<Infallible, SimpleMarker<SerializeMe>>::serialize(
+            &( $ecs.read_storage::<$type>(), ),
+            &$data.0,
+            &$data.1,
+            &mut $ser,
+        )
+        .expect("Unable to serialize components");
+        )*
+    };
+}
+
+macro_rules! deserialize_individually {
+    ($ecs:expr, $de:expr, $data:expr, $( $type:ty),*) => {
+        $(
+        DeserializeComponents::<Infallible, _>::deserialize(
+            &mut ( &mut $ecs.write_storage::<$type>(), ),
+            &mut $data.0, // entities
+            &mut $data.1, // marker
+            &mut $data.2, // allocator
+            &mut $de,
+        )
+        .expect("Unable to deserialize components");
+        )*
+    };
+}
+
+/// Saves the world to the save file, to be resumed in `run_state` once loaded.
+pub fn save_game(ecs: &mut World, run_state: RunState) {
+    let writer = File::create(SAVE_FILE).expect("Unable to create save file");
+    write_save(ecs, run_state, writer);
+}
+
+/// Writes the world to `writer` the way `save_game` saves it.
+pub fn write_save<W: Write>(ecs: &mut World, run_state: RunState, writer: W) {
+    let run_state = match run_state {
+        RunState::ShowTargeting { .. } => RunState::ShowInventory,
+        RunState::SaveGame => RunState::AwaitingInput,
+        other => other,
+    };
+
+    // Resources aren't entities, so they ride along on a helper entity
+    // that only lives for the duration of the save.
+    let helper = {
+        let map = (*ecs.fetch::<Map>()).clone();
+        let log = (*ecs.fetch::<GameLog>()).clone();
+        let god_mode = ecs.fetch::<GodMode>().0;
+        let rng = (*ecs.fetch::<rltk::RandomNumberGenerator>()).clone();
+        let seed = ecs.fetch::<RunSeed>().0;
+        let run_stats = ecs.fetch::<RunStats>();
+
+        SerializationHelper {
+            map,
+            log,
+            god_mode,
+            rng,
+            seed,
+            kills: run_stats.kills,
+            last_hurt_by: run_stats.last_hurt_by.clone(),
+            turns: run_stats.turns,
+            run_state,
+        }
+    };
+    let helper_entity = ecs
+        .create_entity()
+        .with(helper)
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+
+    {
+        let data = (
+            ecs.entities(),
+            ecs.read_storage::<SimpleMarker<SerializeMe>>(),
+        );
+
+        let mut serializer = serde_json::Serializer::new(writer);
+        serialize_individually!(
+            ecs,
+            serializer,
+            data,
+            LastPathUpdate,
+            InInventory,
+            WantsToPickupItem,
+            WantsToUseItem,
+            WantsToDropItem,
+            WantsToDestroyItem,
+            Item,
+            Consumable,
+            ProvidesFood,
+            HungerClock,
+            Ranged,
+            InflictsDamage,
+            AreaOfEffect,
+            Confusion,
+            Poisoned,
+            Asleep,
+            Initiative,
+            Equippable,
+            Equipped,
+            MeleePowerBonus,
+            DefenseBonus,
+            Potion,
+            WantsToMelee,
+            CombatStats,
+            BlocksTile,
+            BlocksVisibility,
+            Door,
+            Key,
+            WantsToOpenDoor,
+            Hidden,
+            EntryTrigger,
+            SingleActivation,
+            Teleports,
+            Name,
+            Monster,
+            Viewshed,
+            Position,
+            Renderable,
+            Player,
+            Experience,
+            SerializationHelper
+        );
+    }
+
+    ecs.delete_entity(helper_entity)
+        .expect("Unable to delete the serialization helper");
+}
+
+/// Fills a freshly registered `World` with the content of the save file,
+/// and returns the run state to carry on in.
+pub fn load_game(ecs: &mut World) -> RunState {
+    let data = fs::read_to_string(SAVE_FILE).expect("Unable to read save file");
+    read_save(ecs, &data)
+}
+
+/// Like `load_game`, but with a save written by `write_save`.
+pub fn read_save(ecs: &mut World, data: &str) -> RunState {
+    {
+        let to_delete: Vec<Entity> = ecs.entities().join().collect();
+        for entity in to_delete {
+            ecs.delete_entity(entity).expect("Unable to delete entity");
+        }
+    }
+
+    let mut deserializer = serde_json::Deserializer::from_str(data);
+
+    {
+        let mut data = (
+            &mut ecs.entities(),
+            &mut ecs.write_storage::<SimpleMarker<SerializeMe>>(),
+            &mut ecs.write_resource::<SimpleMarkerAllocator<SerializeMe>>(),
+        );
+        deserialize_individually!(
+            ecs,
+            deserializer,
+            data,
+            LastPathUpdate,
+            InInventory,
+            WantsToPickupItem,
+            WantsToUseItem,
+            WantsToDropItem,
+            WantsToDestroyItem,
+            Item,
+            Consumable,
+            ProvidesFood,
+            HungerClock,
+            Ranged,
+            InflictsDamage,
+            AreaOfEffect,
+            Confusion,
+            Poisoned,
+            Asleep,
+            Initiative,
+            Equippable,
+            Equipped,
+            MeleePowerBonus,
+            DefenseBonus,
+            Potion,
+            WantsToMelee,
+            CombatStats,
+            BlocksTile,
+            BlocksVisibility,
+            Door,
+            Key,
+            WantsToOpenDoor,
+            Hidden,
+            EntryTrigger,
+            SingleActivation,
+            Teleports,
+            Name,
+            Monster,
+            Viewshed,
+            Position,
+            Renderable,
+            Player,
+            Experience,
+            SerializationHelper
+        );
+    }
+
+    let mut helper_entity: Option<Entity> = None;
+    let mut run_state = RunState::PreRun;
+    {
+        let entities = ecs.entities();
+        let helpers = ecs.read_storage::<SerializationHelper>();
+
+        for (entity, helper) in (&entities, &helpers).join() {
+            let mut map = helper.map.clone();
+            map.tile_content = vec![Vec::new(); map.tiles.len()];
+            map.view_blocked = vec![false; map.tiles.len()];
+            map.closed_doors = vec![false; map.tiles.len()];
+            *ecs.write_resource::<Map>() = map;
+            *ecs.write_resource::<GameLog>() = helper.log.clone();
+            *ecs.write_resource::<GodMode>() = GodMode(helper.god_mode);
+            *ecs.write_resource::<rltk::RandomNumberGenerator>() = helper.rng.clone();
+            *ecs.write_resource::<RunSeed>() = RunSeed(helper.seed);
+            *ecs.write_resource::<RunStats>() = RunStats {
+                kills: helper.kills,
+                last_hurt_by: helper.last_hurt_by.clone(),
+                turns: helper.turns,
+            };
+            run_state = helper.run_state;
+            helper_entity = Some(entity);
+        }
+    }
+
+    {
+        let entities = ecs.entities();
+        let players = ecs.read_storage::<Player>();
+        let positions = ecs.read_storage::<Position>();
+
+        for (entity, _player, pos) in (&entities, &players, &positions).join() {
+            *ecs.write_resource::<Point>() = Point::new(pos.x, pos.y);
+            *ecs.write_resource::<Entity>() = entity;
+        }
+    }
+
+    ecs.delete_entity(helper_entity.expect("Save file has no serialization helper"))
+        .expect("Unable to delete the serialization helper");
+
+    // Turns aren't saved. A save made while the player was deciding what
+    // to do gives them their turn back.
+    let waiting_on_player = !matches!(
+        run_state,
+        RunState::PreRun | RunState::PlayerTurn | RunState::Ticking | RunState::NextLevel
+    );
+    if waiting_on_player {
+        let player_entity = *ecs.fetch::<Entity>();
+        ecs.write_storage::<MyTurn>()
+            .insert(player_entity, MyTurn {})
+            .expect("Unable to insert turn");
+    }
+
+    run_state
+}
+
+pub fn does_save_exist() -> bool {
+    Path::new(SAVE_FILE).exists()
+}
+
+/// Permadeath: a loaded save goes away, so a character that dies after
+/// loading can't be loaded again.
+pub fn delete_save() {
+    if does_save_exist() {
+        fs::remove_file(SAVE_FILE).expect("Unable to delete save file");
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{perform_action, saveload_system, PlayerAction, RunState, State};
+use specs::prelude::*;
+
+/// Runs the game without an Rltk window. Player actions come from a script
+/// instead of the keyboard, so whole runs can be played in tests or on a
+/// headless server.
+pub struct Simulation {
+    state: State,
+}
+
+impl Simulation {
+    /// Sets up a run from `seed` and plays it up to the player's first turn.
+    pub fn new(seed: u64) -> Simulation {
+        let mut state = State::new(seed);
+        state.ecs.insert(RunState::PreRun);
+
+        let mut simulation = Simulation { state };
+        simulation.state.run_until_input();
+        simulation
+    }
+
+    /// Saves the run the way the game does, into a string instead of the
+    /// save file.
+    pub fn save(&mut self) -> String {
+        let run_state = self.run_state();
+        let mut save = Vec::new();
+        saveload_system::write_save(&mut self.state.ecs, run_state, &mut save);
+        String::from_utf8(save).expect("Save is not valid UTF-8")
+    }
+
+    /// Resumes a run from a save made by `save`, the way the game's Load
+    /// Game does.
+    pub fn load(save: &str) -> Simulation {
+        let mut state = State::new(0);
+        let run_state = saveload_system::read_save(&mut state.ecs, save);
+        // Rebuilds what isn't saved, like the map's content index. Nobody
+        // acts, as turns are only taken while ticking.
+        state.run_systems();
+        state.set_run_state(run_state);
+
+        let mut simulation = Simulation { state };
+        simulation.state.run_until_input();
+        simulation
+    }
+
+    pub fn ecs(&self) -> &World {
+        &self.state.ecs
+    }
+
+    pub fn ecs_mut(&mut self) -> &mut World {
+        &mut self.state.ecs
+    }
+
+    pub fn run_state(&self) -> RunState {
+        *self.state.ecs.fetch::<RunState>()
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.run_state() == RunState::GameOver
+    }
+
+    /// Plays `action` and everything that follows until the player is asked
+    /// for input again. Returns `AwaitingInput`, or `GameOver` once the
+    /// player is dead; actions sent after that are ignored.
+    pub fn play(&mut self, action: PlayerAction) -> RunState {
+        if self.run_state() != RunState::AwaitingInput {
+            return self.run_state();
+        }
+
+        let newrunstate = perform_action(&mut self.state, action);
+        self.state.set_run_state(newrunstate);
+        self.state.run_until_input();
+        self.run_state()
+    }
+
+    /// Plays actions in order until the script runs out or the player dies.
+    /// A recorded run plays back with
+    /// `play_script(replay.actions.iter().map(|recorded| recorded.action))`.
+    pub fn play_script<I>(&mut self, actions: I) -> RunState
+    where
+        I: IntoIterator<Item = PlayerAction>,
+    {
+        for action in actions {
+            if self.play(action) == RunState::GameOver {
+                break;
+            }
+        }
+        self.run_state()
+    }
+}
@@ -0,0 +1,94 @@
+use rltk::RandomNumberGenerator;
+
+pub struct SpawnEntry {
+    pub name: String,
+    pub weight: i32,
+}
+
+/// Picks what to spawn, each entry being chosen in proportion to its weight.
+///
+/// `SpawnTable::new().add("Goblin", 10).add("Orc", 5)` spawns goblins two
+/// times out of three.
+#[derive(Default)]
+pub struct SpawnTable {
+    entries: Vec<SpawnEntry>,
+    total_weight: i32,
+}
+
+impl SpawnTable {
+    pub fn new() -> SpawnTable {
+        SpawnTable::default()
+    }
+
+    /// Entries that can never be picked are left out.
+    pub fn add<S: ToString>(mut self, name: S, weight: i32) -> SpawnTable {
+        if weight > 0 {
+            self.total_weight += weight;
+            self.entries.push(SpawnEntry {
+                name: name.to_string(),
+                weight,
+            });
+        }
+        self
+    }
+
+    /// Returns `None` if the table is empty.
+    pub fn roll(&self, rng: &mut RandomNumberGenerator) -> Option<&str> {
+        if self.total_weight == 0 {
+            return None;
+        }
+
+        let mut roll = rng.roll_dice(1, self.total_weight) - 1;
+        for entry in self.entries.iter() {
+            if roll < entry.weight {
+                return Some(&entry.name);
+            }
+            roll -= entry.weight;
+        }
+
+        None
+    }
+
+    pub fn entries(&self) -> &[SpawnEntry] {
+        &self.entries
+    }
+
+    pub fn total_weight(&self) -> i32 {
+        self.total_weight
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpawnTable;
+    use rltk::RandomNumberGenerator;
+
+    #[test]
+    fn empty_table_rolls_nothing() {
+        let mut rng = RandomNumberGenerator::seeded(1);
+        assert_eq!(SpawnTable::new().roll(&mut rng), None);
+    }
+
+    #[test]
+    fn zero_weight_table_rolls_nothing() {
+        let mut rng = RandomNumberGenerator::seeded(1);
+        let table = SpawnTable::new().add("Orc", 0).add("Goblin", 0);
+        assert_eq!(table.total_weight(), 0);
+        assert_eq!(table.roll(&mut rng), None);
+    }
+
+    #[test]
+    fn rolls_only_weighted_entries() {
+        let mut rng = RandomNumberGenerator::seeded(1);
+        let table = SpawnTable::new()
+            .add("Goblin", 10)
+            .add("Orc", 0)
+            .add("Ogre", 5);
+
+        let rolls: Vec<&str> = (0..1000).filter_map(|_| table.roll(&mut rng)).collect();
+        assert_eq!(rolls.len(), 1000);
+        assert!(!rolls.contains(&"Orc"));
+        assert!(rolls.contains(&"Goblin"));
+        assert!(rolls.contains(&"Ogre"));
+    }
+}
@@ -1,11 +1,18 @@
-use crate::{Item, Potion, Rect, MAP_WIDTH, LastPathUpdate};
+use crate::{
+    load_raws, spawn_named_entity, Experience, HungerClock, HungerState, Initiative, Map, Rect,
+    SerializeMe, TileType, NORMAL_SPEED, WELL_FED_TURNS,
+};
 
-use super::{BlocksTile, CombatStats, Monster, Name, Player, Position, Renderable, Viewshed};
+use super::{CombatStats, Name, Player, Position, Renderable, Viewshed};
 use rltk::{RandomNumberGenerator, RGB};
 use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
 
 const MAX_MONSTERS: i32 = 4;
 const MAX_ITEMS: i32 = 2;
+const MAX_TRAPS: i32 = 2;
+/// One door in this many is locked.
+const LOCKED_DOOR_ODDS: i32 = 6;
 
 pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
     ecs.create_entity()
@@ -33,133 +40,172 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
             defense: 2,
             power: 5,
         })
+        .with(Experience { level: 1, xp: 0 })
+        .with(Initiative {
+            speed: NORMAL_SPEED,
+            energy: 0,
+        })
+        .with(HungerClock {
+            state: HungerState::WellFed,
+            duration: WELL_FED_TURNS,
+        })
+        .marked::<SimpleMarker<SerializeMe>>()
         .build()
 }
 
-pub fn spawn_room(ecs: &mut World, room: &Rect) {
-    let mut monster_spawn_points: Vec<i32> = Vec::new();
-    let mut item_spawn_points: Vec<i32> = Vec::new();
+pub fn spawn_room(ecs: &mut World, map: &Map, room: &Rect, map_depth: i32) {
+    let mut possible_targets: Vec<usize> = Vec::new();
+
+    for y in room.y1 + 1..=room.y2 {
+        for x in room.x1 + 1..=room.x2 {
+            possible_targets.push(map.map_index(x, y));
+        }
+    }
+
+    spawn_region(ecs, map, &possible_targets, map_depth);
+}
+
+/// Spawns monsters, items and traps on distinct tiles picked from `area`.
+/// Deeper levels get more monsters, and tougher ones.
+pub fn spawn_region(ecs: &mut World, map: &Map, area: &[usize], map_depth: i32) {
+    let mut monster_spawn_points: Vec<usize> = Vec::new();
+    let mut item_spawn_points: Vec<usize> = Vec::new();
+    let mut trap_spawn_points: Vec<usize> = Vec::new();
 
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        let num_monsters = rng.roll_dice(1, MAX_MONSTERS + 2) - 3; // To have a minimum of 0 monsters
+        let num_monsters = rng.roll_dice(1, MAX_MONSTERS + 2) + (map_depth - 1) - 3; // To have a minimum of 0 monsters
         let num_items = rng.roll_dice(1, MAX_ITEMS + 2) - 3;
+        let num_traps = rng.roll_dice(1, MAX_TRAPS + 2) - 3;
+        // Nobody wants a trap on the stairs.
+        let mut free_tiles: Vec<usize> = area
+            .iter()
+            .copied()
+            .filter(|index| map.tiles[*index] != TileType::DownStairs)
+            .collect();
 
         for _i in 0..num_monsters {
-            let mut added = false;
-            while !added {
-                let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-                let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-
-                let index = ((y * MAP_WIDTH as usize) + x) as i32;
-
-                match !monster_spawn_points.contains(&index) {
-                    true => {
-                        monster_spawn_points.push(index);
-                        added = true;
-                    }
-                    false => (),
-                }
+            if free_tiles.is_empty() {
+                break;
             }
+            let choice = rng.range(0, free_tiles.len() as i32) as usize;
+            monster_spawn_points.push(free_tiles.remove(choice));
         }
 
         for _i in 0..num_items {
-            let mut added = false;
-            while !added {
-                let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-                let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-
-                let index = ((y * MAP_WIDTH as usize) + x) as i32;
-
-                match !item_spawn_points.contains(&index) {
-                    true => {
-                        item_spawn_points.push(index);
-                        added = true;
-                    }
-                    false => (),
-                }
+            if free_tiles.is_empty() {
+                break;
+            }
+            let choice = rng.range(0, free_tiles.len() as i32) as usize;
+            item_spawn_points.push(free_tiles.remove(choice));
+        }
+
+        for _i in 0..num_traps {
+            if free_tiles.is_empty() {
+                break;
             }
+            let choice = rng.range(0, free_tiles.len() as i32) as usize;
+            trap_spawn_points.push(free_tiles.remove(choice));
         }
     }
 
     for index in monster_spawn_points.iter() {
-        let x = *index % MAP_WIDTH;
-        let y = *index / MAP_WIDTH;
-        random_monster(ecs, x, y);
+        let x = *index as i32 % map.width;
+        let y = *index as i32 / map.width;
+        random_monster(ecs, x, y, map_depth);
     }
 
     for index in item_spawn_points.iter() {
-        let x = *index % MAP_WIDTH;
-        let y = *index / MAP_WIDTH;
-        health_potion(ecs, x, y);
+        let x = *index as i32 % map.width;
+        let y = *index as i32 / map.width;
+        random_item(ecs, x, y, map_depth);
+    }
+
+    for index in trap_spawn_points.iter() {
+        let x = *index as i32 % map.width;
+        let y = *index as i32 / map.width;
+        random_trap(ecs, x, y, map_depth);
     }
 }
 
-pub fn random_monster(ecs: &mut World, x: i32, y: i32) {
-    let roll: i32;
-    {
+pub fn random_monster(ecs: &mut World, x: i32, y: i32, map_depth: i32) {
+    let table = load_raws().monster_spawn_table(map_depth);
+    let name = {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        roll = rng.roll_dice(1, 2);
-    }
-    match roll {
-        1 => orc(ecs, x, y),
-        _ => goblin(ecs, x, y),
+        table.roll(&mut rng)
+    };
+
+    if let Some(monster) = name.and_then(|name| spawn_named_entity(ecs, name, Position { x, y })) {
+        scale_to_depth(ecs, monster, map_depth);
     }
 }
 
-fn orc(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('o'), "Orc");
+/// Every level below the first makes monsters a bit sturdier,
+/// and every other level makes them hit harder.
+fn scale_to_depth(ecs: &mut World, monster: Entity, map_depth: i32) {
+    if let Some(stats) = ecs.write_storage::<CombatStats>().get_mut(monster) {
+        stats.max_hp += (map_depth - 1) * 2;
+        stats.hp = stats.max_hp;
+        stats.power += (map_depth - 1) / 2;
+    }
+    if let Some(experience) = ecs.write_storage::<Experience>().get_mut(monster) {
+        experience.level = map_depth;
+    }
 }
-fn goblin(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('g'), "Goblin");
+
+pub fn random_item(ecs: &mut World, x: i32, y: i32, map_depth: i32) {
+    let table = load_raws().item_spawn_table(map_depth);
+    let name = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        table.roll(&mut rng)
+    };
+
+    if let Some(name) = name {
+        spawn_named_entity(ecs, name, Position { x, y });
+    }
 }
 
-fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: rltk::FontCharType, name: S) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph,
-            fg: RGB::named(rltk::RED),
-            bg: RGB::named(rltk::BLACK),
-        })
-        .with(Viewshed {
-            visible_tiles: Vec::new(),
-            range: 8,
-            dirty: true,
-        })
-        .with(Monster {
-            last_known_player_pos: None,
-            last_pathfind: None,
-            next_step: 0
-        })
-        .with(Name {
-            name: name.to_string(),
-        })
-        .with(BlocksTile {})
-        .with(CombatStats {
-            max_hp: 16,
-            hp: 16,
-            defense: 1,
-            power: 4,
-        })
-        .with(LastPathUpdate {
-            nb_frames: 0
-        })
-        .build();
+pub fn random_trap(ecs: &mut World, x: i32, y: i32, map_depth: i32) {
+    let table = load_raws().trap_spawn_table(map_depth);
+    let name = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        table.roll(&mut rng)
+    };
+
+    if let Some(name) = name {
+        spawn_named_entity(ecs, name, Position { x, y });
+    }
 }
 
-fn health_potion(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('¡'),
-            fg: RGB::named(rltk::MAGENTA),
-            bg: RGB::named(rltk::BLACK),
-        })
-        .with(Name {
-            name: "Health Potion".to_string(),
-        })
-        .with(Item {})
-        .with(Potion { heal_amount: 8 })
-        .build();
+/// Puts a door in every doorway. Some of them are locked, and for each of
+/// those a key is left in `key_room`, where the player starts, so no part
+/// of the level is ever shut off for good.
+pub fn spawn_doors(ecs: &mut World, map: &Map, doorways: &[usize], key_room: &Rect) {
+    let mut locked_doors = 0;
+    for index in doorways.iter() {
+        let locked = {
+            let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+            rng.roll_dice(1, LOCKED_DOOR_ODDS) == 1
+        };
+        if locked {
+            locked_doors += 1;
+        }
+        let name = if locked { "Locked Door" } else { "Door" };
+        let pos = Position {
+            x: *index as i32 % map.width,
+            y: *index as i32 / map.width,
+        };
+        spawn_named_entity(ecs, name, pos).expect("Doors are missing from the raws");
+    }
+
+    for _i in 0..locked_doors {
+        let (x, y) = {
+            let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+            (
+                rng.range(key_room.x1 + 1, key_room.x2 + 1),
+                rng.range(key_room.y1 + 1, key_room.y2 + 1),
+            )
+        };
+        spawn_named_entity(ecs, "Key", Position { x, y }).expect("Keys are missing from the raws");
+    }
 }
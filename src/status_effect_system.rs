@@ -0,0 +1,107 @@
+use crate::{GameLog, LogCategory, LogEntry, Renderable, RunState, RunStats};
+
+use super::{Asleep, Confusion, MyTurn, Name, Poisoned, SufferDamage};
+use specs::prelude::*;
+
+/// Ticks an entity's timed status effects down once for every turn it gets,
+/// so faster creatures shake them off sooner.
+pub struct StatusEffectSystem {}
+
+impl<'a> System<'a> for StatusEffectSystem {
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, RunState>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, Confusion>,
+        WriteStorage<'a, Poisoned>,
+        WriteStorage<'a, Asleep>,
+        WriteStorage<'a, SufferDamage>,
+        WriteExpect<'a, RunStats>,
+        ReadStorage<'a, Renderable>,
+        ReadStorage<'a, MyTurn>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            runstate,
+            mut gamelog,
+            entities,
+            names,
+            mut confused,
+            mut poisoned,
+            mut asleep,
+            mut suffer_damage,
+            mut run_stats,
+            renderables,
+            turns,
+        ) = data;
+
+        if *runstate != RunState::Ticking {
+            return;
+        }
+
+        let mut expired: Vec<Entity> = Vec::new();
+        for (entity, confusion, _turn) in (&entities, &mut confused, &turns).join() {
+            confusion.turns -= 1;
+            if confusion.turns < 1 {
+                expired.push(entity);
+                if entity == *player_entity {
+                    gamelog.system("You are no longer confused.");
+                }
+            }
+        }
+        for entity in expired.drain(..) {
+            confused.remove(entity);
+        }
+
+        for (entity, poison, name, _turn) in (&entities, &mut poisoned, &names, &turns).join() {
+            SufferDamage::new_damage(
+                &mut suffer_damage,
+                entity,
+                poison.dmg_per_turn,
+                poison.source.0,
+            );
+            if entity == *player_entity {
+                run_stats.last_hurt_by = "Poison".to_string();
+                gamelog.log(
+                    LogEntry::new(LogCategory::Combat)
+                        .text("You suffer ")
+                        .damage(poison.dmg_per_turn)
+                        .text(" hp of poison damage."),
+                );
+            } else {
+                gamelog.log(
+                    LogEntry::new(LogCategory::Combat)
+                        .name(&name.name, renderables.get(entity))
+                        .text(" suffers ")
+                        .damage(poison.dmg_per_turn)
+                        .text(" hp of poison damage."),
+                );
+            }
+
+            poison.turns -= 1;
+            if poison.turns < 1 {
+                expired.push(entity);
+            }
+        }
+        for entity in expired.drain(..) {
+            poisoned.remove(entity);
+        }
+
+        for (entity, sleep, _turn) in (&entities, &mut asleep, &turns).join() {
+            sleep.turns -= 1;
+            if sleep.turns < 1 {
+                expired.push(entity);
+                if entity == *player_entity {
+                    gamelog.system("You wake up.");
+                }
+            }
+        }
+        for entity in expired.drain(..) {
+            asleep.remove(entity);
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use crate::{
+    perform_action, screen_to_world, EntryTrigger, GameLog, Hidden, Map, Monster, Name,
+    PlayerAction, Position, RunState, State, TrapAwareMap, Viewshed, VIEW_HEIGHT, VIEW_WIDTH,
+};
+
+use rltk::{DijkstraMap, Point, Rltk};
+use specs::prelude::*;
+
+/// How far auto-explore looks for unrevealed tiles.
+const EXPLORE_DEPTH: f32 = 1024.0;
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum TravelGoal {
+    /// A revealed tile the player clicked on.
+    Tile(usize),
+    /// Whichever unrevealed tile is nearest, until none can be reached.
+    Explore,
+}
+
+/// Walks the player one step per turn without a key press for each,
+/// until they get there or something needs their attention.
+pub struct Travel {
+    goal: TravelGoal,
+    /// `GameLog::logged` when travel started. Anything logged since stops it.
+    logged: usize,
+    /// Monsters seen on the last step, so only newcomers stop travel.
+    monsters_in_view: Vec<Entity>,
+}
+
+impl Travel {
+    pub fn new(ecs: &World, goal: TravelGoal) -> Travel {
+        Travel {
+            goal,
+            logged: ecs.fetch::<GameLog>().logged(),
+            monsters_in_view: monsters_in_view(ecs),
+        }
+    }
+
+    /// Where to move next, or `None` once travel is over.
+    fn next_step(&mut self, ecs: &World) -> Option<(i32, i32)> {
+        if self.interrupted(ecs) {
+            return None;
+        }
+
+        let map = ecs.fetch::<Map>();
+        let player_pos = *ecs.fetch::<Point>();
+        let start = map.map_index(player_pos.x, player_pos.y);
+        let known_traps = known_traps(ecs, &map);
+
+        let next = match self.goal {
+            TravelGoal::Tile(destination) => {
+                if start == destination {
+                    return None;
+                }
+                let trap_aware = TrapAwareMap {
+                    map: &map,
+                    known_traps: &known_traps,
+                    goal: Some(destination),
+                };
+                let path = rltk::a_star_search(start, destination, &trap_aware);
+                if !path.success || path.steps.len() < 2 {
+                    let mut gamelog = ecs.fetch_mut::<GameLog>();
+                    gamelog.system("You can't find a way there.");
+                    return None;
+                }
+                path.steps[1]
+            }
+            TravelGoal::Explore => match explore_step(&map, &known_traps, start) {
+                Some(next) => next,
+                None => {
+                    let mut gamelog = ecs.fetch_mut::<GameLog>();
+                    gamelog.system("There is nothing left to explore here.");
+                    return None;
+                }
+            },
+        };
+
+        // Only a trap the player asked to go to is left on the way.
+        if known_traps.contains(&next) {
+            let mut gamelog = ecs.fetch_mut::<GameLog>();
+            gamelog.system("You stop, there is a trap in the way.");
+            return None;
+        }
+
+        Some((
+            next as i32 % map.width - player_pos.x,
+            next as i32 / map.width - player_pos.y,
+        ))
+    }
+
+    /// True if something was logged or a monster came into view since
+    /// travel started.
+    fn interrupted(&mut self, ecs: &World) -> bool {
+        if ecs.fetch::<GameLog>().logged() != self.logged {
+            return true;
+        }
+
+        let in_view = monsters_in_view(ecs);
+        let newcomer = in_view
+            .iter()
+            .find(|monster| !self.monsters_in_view.contains(monster))
+            .copied();
+        self.monsters_in_view = in_view;
+
+        match newcomer {
+            Some(monster) => {
+                let names = ecs.read_storage::<Name>();
+                if let Some(name) = names.get(monster) {
+                    let mut gamelog = ecs.fetch_mut::<GameLog>();
+                    gamelog.system(format!("You stop, a {} comes into view.", name.name));
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn monsters_in_view(ecs: &World) -> Vec<Entity> {
+    let player_entity = ecs.fetch::<Entity>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    let monsters = ecs.read_storage::<Monster>();
+    let positions = ecs.read_storage::<Position>();
+    let entities = ecs.entities();
+
+    let viewshed = match viewsheds.get(*player_entity) {
+        Some(viewshed) => viewshed,
+        None => return Vec::new(),
+    };
+
+    (&entities, &monsters, &positions)
+        .join()
+        .filter(|(_, _, pos)| viewshed.visible_tiles.contains(&Point::new(pos.x, pos.y)))
+        .map(|(entity, _, _)| entity)
+        .collect()
+}
+
+/// Revealed tiles with a trap on them that is out in the open.
+fn known_traps(ecs: &World, map: &Map) -> Vec<usize> {
+    let triggers = ecs.read_storage::<EntryTrigger>();
+    let hidden = ecs.read_storage::<Hidden>();
+    let positions = ecs.read_storage::<Position>();
+
+    (&triggers, !&hidden, &positions)
+        .join()
+        .map(|(_, _, pos)| map.map_index(pos.x, pos.y))
+        .filter(|index| map.revealed_tiles[*index])
+        .collect()
+}
+
+/// The first step downhill on a Dijkstra map flowing from every tile the
+/// player hasn't seen yet, around the traps they know of.
+fn explore_step(map: &Map, known_traps: &[usize], start: usize) -> Option<usize> {
+    let unrevealed: Vec<usize> = map
+        .revealed_tiles
+        .iter()
+        .enumerate()
+        .filter(|(_, revealed)| !**revealed)
+        .map(|(index, _)| index)
+        .collect();
+    if unrevealed.is_empty() {
+        return None;
+    }
+
+    let trap_aware = TrapAwareMap {
+        map,
+        known_traps,
+        goal: None,
+    };
+    let dijkstra = DijkstraMap::new(
+        map.width,
+        map.height,
+        &unrevealed,
+        &trap_aware,
+        EXPLORE_DEPTH,
+    );
+    if dijkstra.map[start] == f32::MAX {
+        return None;
+    }
+    DijkstraMap::find_lowest_exit(&dijkstra, start, &trap_aware)
+}
+
+/// The revealed tile under the mouse, if it was clicked on.
+pub fn clicked_tile(ecs: &World, ctx: &Rltk) -> Option<usize> {
+    if !ctx.left_click {
+        return None;
+    }
+
+    let (mouse_x, mouse_y) = ctx.mouse_pos();
+    if !(0..VIEW_WIDTH).contains(&mouse_x) || !(0..VIEW_HEIGHT).contains(&mouse_y) {
+        return None;
+    }
+
+    let world = screen_to_world(ecs, Point::new(mouse_x, mouse_y));
+    let map = ecs.fetch::<Map>();
+    if world.x < 0 || world.x >= map.width || world.y < 0 || world.y >= map.height {
+        return None;
+    }
+
+    let index = map.map_index(world.x, world.y);
+    if !map.revealed_tiles[index] {
+        return None;
+    }
+    Some(index)
+}
+
+pub fn start_travel(gs: &mut State, goal: TravelGoal) -> RunState {
+    gs.travel = Some(Travel::new(&gs.ecs, goal));
+    travel_step(gs)
+}
+
+/// Takes the next step of the current travel. Each step is an ordinary
+/// move, so it gets recorded and replayed like one.
+pub fn travel_step(gs: &mut State) -> RunState {
+    let step = match gs.travel.as_mut() {
+        Some(travel) => travel.next_step(&gs.ecs),
+        None => None,
+    };
+
+    match step {
+        Some((delta_x, delta_y)) => perform_action(gs, PlayerAction::Move { delta_x, delta_y }),
+        None => {
+            gs.travel = None;
+            RunState::AwaitingInput
+        }
+    }
+}
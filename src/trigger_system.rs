@@ -0,0 +1,157 @@
+use crate::{
+    BlocksTile, EntityMoved, EntryTrigger, GameLog, Hidden, InflictsDamage, LogCategory, LogEntry,
+    Map, Monster, Name, Poisoned, Position, Renderable, RunStats, SingleActivation, SufferDamage,
+    Teleports, TileType, Viewshed,
+};
+
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+/// Sets off the entry triggers under everything that moved this turn.
+pub struct TriggerSystem {}
+
+impl<'a> System<'a> for TriggerSystem {
+    type SystemData = (
+        WriteExpect<'a, Map>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, Point>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RunStats>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        Entities<'a>,
+        WriteStorage<'a, EntityMoved>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Viewshed>,
+        ReadStorage<'a, EntryTrigger>,
+        WriteStorage<'a, Hidden>,
+        ReadStorage<'a, SingleActivation>,
+        ReadStorage<'a, InflictsDamage>,
+        WriteStorage<'a, Poisoned>,
+        ReadStorage<'a, Teleports>,
+        WriteStorage<'a, SufferDamage>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Renderable>,
+        WriteStorage<'a, Monster>,
+        ReadStorage<'a, BlocksTile>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            mut map,
+            player_entity,
+            mut player_pos,
+            mut gamelog,
+            mut run_stats,
+            mut rng,
+            entities,
+            mut moved,
+            mut positions,
+            mut viewsheds,
+            triggers,
+            mut hidden,
+            single_activation,
+            inflicts_damage,
+            mut poisoned,
+            teleports,
+            mut suffer_damage,
+            names,
+            renderables,
+            mut monsters,
+            blockers,
+        ) = data;
+
+        let mut victims: Vec<(Entity, usize)> = Vec::new();
+        for (entity, _moved, pos) in (&entities, &moved, &positions).join() {
+            victims.push((entity, map.map_index(pos.x, pos.y)));
+        }
+        moved.clear();
+
+        let mut spent: Vec<Entity> = Vec::new();
+        for (victim, index) in victims {
+            let tile_content = map.tile_content[index].clone();
+            for trap in tile_content.iter() {
+                if *trap == victim || triggers.get(*trap).is_none() || spent.contains(trap) {
+                    continue;
+                }
+
+                // Whatever went off is no secret any more.
+                hidden.remove(*trap);
+
+                let trap_name = &names.get(*trap).unwrap().name;
+                let is_player = victim == *player_entity;
+                if is_player {
+                    gamelog.log(
+                        LogEntry::new(LogCategory::Combat)
+                            .text("You trigger a ")
+                            .name(trap_name, renderables.get(*trap))
+                            .text("!"),
+                    );
+                } else if let Some(name) = names.get(victim) {
+                    gamelog.log(
+                        LogEntry::new(LogCategory::Combat)
+                            .name(&name.name, renderables.get(victim))
+                            .text(" triggers a ")
+                            .name(trap_name, renderables.get(*trap))
+                            .text("!"),
+                    );
+                }
+
+                if let Some(damage) = inflicts_damage.get(*trap) {
+                    // Traps have no attacker to credit.
+                    SufferDamage::new_damage(&mut suffer_damage, victim, damage.damage, None);
+                    if is_player {
+                        run_stats.last_hurt_by = trap_name.clone();
+                    }
+                }
+
+                if let Some(poison) = poisoned.get(*trap).cloned() {
+                    poisoned
+                        .insert(victim, poison)
+                        .expect("Unable to insert status");
+                }
+
+                if teleports.get(*trap).is_some() {
+                    let destination = random_floor(&map, &mut rng);
+                    if let Some(pos) = positions.get_mut(victim) {
+                        pos.x = destination.x;
+                        pos.y = destination.y;
+                    }
+                    if let Some(viewshed) = viewsheds.get_mut(victim) {
+                        viewshed.dirty = true;
+                    }
+                    // The old path starts where the monster no longer is.
+                    if let Some(monster) = monsters.get_mut(victim) {
+                        monster.last_pathfind = None;
+                    }
+                    // Keep anyone else teleported this turn off the same tile.
+                    if blockers.get(victim).is_some() {
+                        let index = map.map_index(destination.x, destination.y);
+                        map.blocked[index] = true;
+                    }
+                    if is_player {
+                        *player_pos = destination;
+                    }
+                }
+
+                if single_activation.get(*trap).is_some() {
+                    spent.push(*trap);
+                }
+            }
+        }
+
+        for trap in spent {
+            entities.delete(trap).expect("Unable to delete trap");
+        }
+    }
+}
+
+fn random_floor(map: &Map, rng: &mut RandomNumberGenerator) -> Point {
+    loop {
+        let x = rng.range(1, map.width - 1);
+        let y = rng.range(1, map.height - 1);
+        let index = map.map_index(x, y);
+        if map.tiles[index] == TileType::Floor && !map.blocked[index] {
+            return Point::new(x, y);
+        }
+    }
+}
@@ -1,9 +1,12 @@
 #![allow(unused)]
-use crate::{GodMode, Player};
+use crate::{GameLog, GodMode, Hidden, MyTurn, Name, Player, RunState};
 use specs::prelude::*;
 
 use super::{Map, Position, Viewshed};
-use rltk::{field_of_view, Point};
+use rltk::{field_of_view, Point, RandomNumberGenerator};
+
+/// Odds of the player noticing a hidden trap in view, each turn.
+const SPOT_ODDS: i32 = 24;
 
 pub struct VisibilitySystem {}
 
@@ -15,10 +18,29 @@ impl<'a> System<'a> for VisibilitySystem {
         WriteStorage<'a, Position>,
         ReadStorage<'a, Player>,
         Read<'a, GodMode>,
+        WriteStorage<'a, Hidden>,
+        ReadStorage<'a, Name>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        ReadExpect<'a, RunState>,
+        ReadStorage<'a, MyTurn>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, entities, mut viewshed, pos, player, godmode) = data;
+        let (
+            mut map,
+            entities,
+            mut viewshed,
+            pos,
+            player,
+            godmode,
+            mut hidden,
+            names,
+            mut gamelog,
+            mut rng,
+            runstate,
+            turns,
+        ) = data;
         for (ent, viewshed, pos) in (&entities, &mut viewshed, &pos).join() {
             if viewshed.dirty {
                 viewshed.dirty = false;
@@ -46,6 +68,24 @@ impl<'a> System<'a> for VisibilitySystem {
             }
         }
 
+        // Every hidden trap in sight has a small chance of being spotted, once
+        // at the start of each of the player's turns.
+        if *runstate == RunState::Ticking {
+            for (viewshed, _player, _turn) in (&viewshed, &player, &turns).join() {
+                for vis in viewshed.visible_tiles.iter() {
+                    let idx = map.map_index(vis.x, vis.y);
+                    for entity in map.tile_content[idx].iter() {
+                        if hidden.get(*entity).is_some() && rng.roll_dice(1, SPOT_ODDS) == 1 {
+                            hidden.remove(*entity);
+                            if let Some(name) = names.get(*entity) {
+                                gamelog.system(format!("You spot a {}.", name.name));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
         if godmode.0 {
             map.visible_tiles.fill(true);
         }
@@ -0,0 +1,142 @@
+use roguelike::{
+    CombatStats, Equipped, GameLog, InInventory, Map, MyTurn, Name, PlayerAction, Position,
+    RunState, RunStats, Simulation,
+};
+use specs::prelude::*;
+
+const SEED: u64 = 1234;
+
+/// Wanders in every direction, searching and picking things up on the way.
+fn script() -> Vec<PlayerAction> {
+    let directions = [
+        (1, 0),
+        (1, 1),
+        (0, 1),
+        (-1, 1),
+        (-1, 0),
+        (-1, -1),
+        (0, -1),
+        (1, -1),
+    ];
+
+    let mut actions = Vec::new();
+    for step in 0..300 {
+        let (delta_x, delta_y) = directions[(step / 5) % directions.len()];
+        actions.push(PlayerAction::Move { delta_x, delta_y });
+        if step % 7 == 0 {
+            actions.push(PlayerAction::PickUp);
+        }
+        if step % 11 == 0 {
+            actions.push(PlayerAction::Search);
+        }
+    }
+    actions
+}
+
+/// Everything that tells two runs apart: the map, the log, the run stats
+/// and every named entity with its position and health.
+fn snapshot(simulation: &Simulation) -> String {
+    let ecs = simulation.ecs();
+    let map = ecs.fetch::<Map>();
+    let log = ecs.fetch::<GameLog>();
+    let run_stats = ecs.fetch::<RunStats>();
+    let entities = ecs.entities();
+    let names = ecs.read_storage::<Name>();
+    let positions = ecs.read_storage::<Position>();
+    let stats = ecs.read_storage::<CombatStats>();
+
+    let mut lines = vec![
+        serde_json::to_string(&*map).expect("Unable to serialize the map"),
+        serde_json::to_string(&*log).expect("Unable to serialize the log"),
+        format!(
+            "turns {} kills {} hurt by {:?}",
+            run_stats.turns, run_stats.kills, run_stats.last_hurt_by
+        ),
+    ];
+    for (entity, name) in (&entities, &names).join() {
+        let pos = positions.get(entity).map(|pos| (pos.x, pos.y));
+        let hp = stats.get(entity).map(|stats| (stats.hp, stats.max_hp));
+        lines.push(format!("{} {} {:?} {:?}", entity.id(), name.name, pos, hp));
+    }
+    lines.join("\n")
+}
+
+/// What a save has to bring back for the player: where they are, what
+/// they carry and how the run went so far.
+fn player_snapshot(simulation: &Simulation) -> String {
+    let ecs = simulation.ecs();
+    let player = *ecs.fetch::<Entity>();
+    let run_stats = ecs.fetch::<RunStats>();
+    let names = ecs.read_storage::<Name>();
+    let positions = ecs.read_storage::<Position>();
+    let backpack = ecs.read_storage::<InInventory>();
+    let equipped = ecs.read_storage::<Equipped>();
+
+    // Entity ids change across a save, so items are told apart by name.
+    let mut carried: Vec<String> = (&backpack, &names)
+        .join()
+        .filter(|(item, _)| item.owner == player)
+        .map(|(_, name)| name.name.clone())
+        .chain(
+            (&equipped, &names)
+                .join()
+                .filter(|(item, _)| item.owner == player)
+                .map(|(_, name)| format!("{} (equipped)", name.name)),
+        )
+        .collect();
+    carried.sort();
+
+    format!(
+        "at {:?} carrying {:?} turns {} kills {} hurt by {:?}",
+        positions.get(player).map(|pos| (pos.x, pos.y)),
+        carried,
+        run_stats.turns,
+        run_stats.kills,
+        run_stats.last_hurt_by
+    )
+}
+
+#[test]
+fn same_seed_and_script_end_in_the_same_world() {
+    let mut first = Simulation::new(SEED);
+    let first_state = first.play_script(script());
+
+    let mut second = Simulation::new(SEED);
+    let second_state = second.play_script(script());
+
+    assert!(first.ecs().fetch::<RunStats>().turns > 0);
+    assert!(first_state == second_state);
+    assert_eq!(snapshot(&first), snapshot(&second));
+}
+
+#[test]
+fn saved_run_loads_where_it_left_off() {
+    let mut simulation = Simulation::new(SEED);
+    {
+        // Tough enough to live through the script, so the save is made mid-run.
+        let ecs = simulation.ecs_mut();
+        let player = *ecs.fetch::<Entity>();
+        let mut stats = ecs.write_storage::<CombatStats>();
+        let stats = stats.get_mut(player).expect("The player has no stats");
+        stats.max_hp = 10_000;
+        stats.hp = 10_000;
+    }
+    simulation.play_script(script().into_iter().take(150));
+    assert!(simulation.run_state() == RunState::AwaitingInput);
+
+    let save = simulation.save();
+    let mut loaded = Simulation::load(&save);
+
+    assert!(loaded.run_state() == simulation.run_state());
+    assert_eq!(player_snapshot(&loaded), player_snapshot(&simulation));
+
+    // The save was made while waiting on the player, so they get their turn back.
+    {
+        let ecs = loaded.ecs();
+        let player = *ecs.fetch::<Entity>();
+        assert!(ecs.read_storage::<MyTurn>().get(player).is_some());
+    }
+    let turns = loaded.ecs().fetch::<RunStats>().turns;
+    assert!(loaded.play(PlayerAction::Search) == RunState::AwaitingInput);
+    assert_eq!(loaded.ecs().fetch::<RunStats>().turns, turns + 1);
+}